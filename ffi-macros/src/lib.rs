@@ -15,6 +15,9 @@ use syn::{parse_macro_input, Expr, ItemFn};
 /// Also logs `error!` message upon panic so `log` crate must
 /// be in scope.
 /// 
+/// The body is treated as unwind safe since any state it leaves behind
+/// belongs to the caller on the other side of the ffi boundary.
+/// 
 /// ### Example function with no return value:
 /// ```
 ///     # use alass_ffi_macros::catch_panic;
@@ -43,14 +46,17 @@ pub fn catch_panic(args: TokenStream, item: TokenStream) -> TokenStream {
     let ItemFn { attrs, vis, sig, block } = parse_macro_input!(item as ItemFn);
 
     // If args aren't specified, assume function returns void
-    let args = if args.is_empty() { quote!(()).into() } else { args };
-
-    let ret_expr = parse_macro_input!(args as Expr);
+    let ret_expr = if args.is_empty() {
+        quote!()
+    } else {
+        let expr = parse_macro_input!(args as Expr);
+        quote!(#expr)
+    };
 
     let wrapped = quote! {
         #(#attrs)*
         #vis #sig {
-            match std::panic::catch_unwind(|| { #block }) {
+            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| { #block })) {
                 Ok(v) => v,
                 Err(e) => {
                     match e.downcast_ref::<&'static str>() {
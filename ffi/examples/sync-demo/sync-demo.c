@@ -7,6 +7,7 @@
 
 #include <stdio.h>
 #include <string.h>
+#include <strings.h>
 #include <libgen.h>
 #include <getopt.h>
 
@@ -94,15 +95,27 @@ AlassTimeSpans *load_audio_ref_spans(char *ref_file)
 }
 
 //
-// Synchronizes subtitle file at 'sub_in' using the raw audio samples in 'ref_file'. The
-// resulting output is saved to 'sub_out'.
+// Returns true if the given path has a '.wav' extension
+//
+bool is_wav_file(char *path)
+{
+    size_t len = strlen(path);
+    return len >= 4 && strcasecmp(path + len - 4, ".wav") == 0;
+}
+
+//
+// Synchronizes subtitle file at 'sub_in' using the audio in 'ref_file', either a WAVE file
+// or raw audio samples. The resulting output is saved to 'sub_out'.
 //
 AlassResultCode sync_to_audio(char *sub_in, char *sub_out, char *ref_file, float ref_fps, char *sub_enc, AlassSyncOptions *opts)
 {
     // Open reference audio file and compute timespans
-    AlassTimeSpans *ref_spans = load_audio_ref_spans(ref_file);
+    AlassTimeSpans *ref_spans = is_wav_file(ref_file) ?
+        alass_timespans_from_wav(ref_file) :
+        load_audio_ref_spans(ref_file);
     if (ref_spans == NULL)
     {
+      fprintf(stderr, "ERROR: Unable to compute reference timespans from audio file!\n");
       return 1;
     }
 
@@ -149,13 +162,13 @@ void usage(char **argv)
     char *cmd_name = basename(argv[0]);
     fprintf(stderr, "USAGE\n");
     fprintf(stderr, "  %s -s SUB_REF_FILE SUB_IN SUB_OUT\n", cmd_name);
-    fprintf(stderr, "  %s -a PCM_REF_FILE SUB_IN SUB_OUT\n", cmd_name);
+    fprintf(stderr, "  %s -a AUD_REF_FILE SUB_IN SUB_OUT\n", cmd_name);
     fprintf(stderr, "\nARGUMENTS\n");
     fprintf(stderr, "  SUB_IN    Subtitle file with incorrect timing.\n");
     fprintf(stderr, "  SUB_OUT   Output location of fixed subtitle file.\n");
     fprintf(stderr, "\nOPTIONS\n");
     fprintf(stderr, "  -s, --ref-sub REF_SUB_FILE    Correctly-timed reference subtitle file to which to sync.\n");
-    fprintf(stderr, "  -a, --ref-audio REF_PCM_FILE  Reference audio file to which to sync (raw 8kHz mono 16bit signed little-endian).\n");
+    fprintf(stderr, "  -a, --ref-audio REF_AUD_FILE  Reference audio file to which to sync (WAVE, or raw 8kHz mono 16bit signed little-endian).\n");
     fprintf(stderr, "  -n, --no-split                Disable alass \"split mode\".\n");
     fprintf(stderr, "  -p, --split-penalty FLOAT     The penalty applied to each split when using \"split mode\". (default 7.0)\n");
     fprintf(stderr, "  -i, --interval MILLIS         Smallest recognized time interval by alass.\n");
@@ -0,0 +1,196 @@
+extern crate alass_util;
+
+use crate::util::*;
+use crate::result_codes::*;
+use crate::catch_panic;
+
+use alass_util::{AudioFormat, ChannelFusion, ChannelLayout, Downmix, SampleFormat};
+
+use std::ptr;
+
+use log::error;
+
+pub type ChannelLayoutCode = u32;
+
+#[no_mangle] pub static ALASS_LAYOUT_UNKNOWN: ChannelLayoutCode = 0;
+#[no_mangle] pub static ALASS_LAYOUT_MONO:    ChannelLayoutCode = 1;
+#[no_mangle] pub static ALASS_LAYOUT_STEREO:  ChannelLayoutCode = 2;
+#[no_mangle] pub static ALASS_LAYOUT_5_1:     ChannelLayoutCode = 3;
+#[no_mangle] pub static ALASS_LAYOUT_7_1:     ChannelLayoutCode = 4;
+
+pub type DownmixCode = u32;
+
+#[no_mangle] pub static ALASS_DOWNMIX_AVERAGE:       DownmixCode = 0;
+#[no_mangle] pub static ALASS_DOWNMIX_CHANNEL:       DownmixCode = 1;
+#[no_mangle] pub static ALASS_DOWNMIX_DIALOGUE:      DownmixCode = 2;
+#[no_mangle] pub static ALASS_DOWNMIX_FUSE_ANY:      DownmixCode = 3;
+#[no_mangle] pub static ALASS_DOWNMIX_FUSE_ALL:      DownmixCode = 4;
+#[no_mangle] pub static ALASS_DOWNMIX_FUSE_MAJORITY: DownmixCode = 5;
+
+pub type SampleFormatCode = u32;
+
+#[no_mangle] pub static ALASS_SAMPLE_U8:  SampleFormatCode = 0;
+#[no_mangle] pub static ALASS_SAMPLE_I16: SampleFormatCode = 1;
+#[no_mangle] pub static ALASS_SAMPLE_I32: SampleFormatCode = 2;
+#[no_mangle] pub static ALASS_SAMPLE_F32: SampleFormatCode = 3;
+
+///
+/// Creates a new audio format descriptor for interleaved audio with the given sample rate (Hz)
+/// and channel count. Returns null if either is unsupported.
+/// 
+/// The conventional layout for the channel count is assumed (see `alass_audio_format_set_layout()`)
+/// along with a dialogue-weighted downmix (see `alass_audio_format_set_downmix()`).
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_audio_format_new(sample_rate: u32, channels: u32) -> *mut AudioFormat {
+    let format = AudioFormat::interleaved(sample_rate, channels as usize);
+    match format.validate() {
+        Ok(()) => to_ptr(format),
+        Err(e) => {
+            error!("Invalid parameter: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+///
+/// Sets the channel layout (one of the `ALASS_LAYOUT_*` constants)
+/// 
+/// Channels are expected in the conventional WAVE/SMPTE order: stereo is L, R; 5.1 is
+/// L, R, C, LFE, Ls, Rs; 7.1 is L, R, C, LFE, Lb, Rb, Ls, Rs. The layout must agree with
+/// the channel count unless `ALASS_LAYOUT_UNKNOWN`.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_format_set_layout(format: *mut AudioFormat, layout: ChannelLayoutCode) -> ResultCode {
+    if format.is_null() {
+        error!("Invalid parameter: AudioFormat pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let layout = if layout == ALASS_LAYOUT_UNKNOWN {
+        ChannelLayout::Unknown
+    } else if layout == ALASS_LAYOUT_MONO {
+        ChannelLayout::Mono
+    } else if layout == ALASS_LAYOUT_STEREO {
+        ChannelLayout::Stereo
+    } else if layout == ALASS_LAYOUT_5_1 {
+        ChannelLayout::Surround51
+    } else if layout == ALASS_LAYOUT_7_1 {
+        ChannelLayout::Surround71
+    } else {
+        error!("Invalid parameter: unknown channel layout (layout={})", layout);
+        return ALASS_INVALID_PARAMS;
+    };
+
+    let f = from_ptr(format);
+    update_format(f, AudioFormat { layout, ..f.clone() })
+}
+
+///
+/// Sets how multi-channel audio is mixed down to mono before voice-activity detection
+/// 
+/// * `ALASS_DOWNMIX_AVERAGE`: Equal contribution from every channel.
+/// * `ALASS_DOWNMIX_CHANNEL`: Use only the channel at index `channel`.
+/// * `ALASS_DOWNMIX_DIALOGUE`: Favor the center channel, where dialogue is mixed. Falls
+///    back to averaging for unknown layouts. (default)
+/// 
+/// Rather than mixing down, the `ALASS_DOWNMIX_FUSE_*` modes detect voice activity on the
+/// center and front channels separately (every channel for unknown layouts) and combine the
+/// results, so that effects panned to the sides do not mask dialogue. Slower by the number
+/// of channels analyzed.
+/// 
+/// * `ALASS_DOWNMIX_FUSE_ANY`: Speech on any channel (center OR front).
+/// * `ALASS_DOWNMIX_FUSE_ALL`: Speech on every channel (center AND front).
+/// * `ALASS_DOWNMIX_FUSE_MAJORITY`: Speech on more than half of the channels.
+/// 
+/// The `channel` parameter is ignored unless `mode` is `ALASS_DOWNMIX_CHANNEL`.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_format_set_downmix(format: *mut AudioFormat, mode: DownmixCode, channel: u32) -> ResultCode {
+    if format.is_null() {
+        error!("Invalid parameter: AudioFormat pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let downmix = if mode == ALASS_DOWNMIX_AVERAGE {
+        Downmix::Average
+    } else if mode == ALASS_DOWNMIX_CHANNEL {
+        Downmix::Channel(channel as usize)
+    } else if mode == ALASS_DOWNMIX_DIALOGUE {
+        Downmix::Dialogue
+    } else if mode == ALASS_DOWNMIX_FUSE_ANY {
+        Downmix::Fused(ChannelFusion::Any)
+    } else if mode == ALASS_DOWNMIX_FUSE_ALL {
+        Downmix::Fused(ChannelFusion::All)
+    } else if mode == ALASS_DOWNMIX_FUSE_MAJORITY {
+        Downmix::Fused(ChannelFusion::Majority)
+    } else {
+        error!("Invalid parameter: unknown downmix mode (mode={})", mode);
+        return ALASS_INVALID_PARAMS;
+    };
+
+    let f = from_ptr(format);
+    update_format(f, AudioFormat { downmix, ..f.clone() })
+}
+
+///
+/// Sets the encoding of samples passed to `alass_audio_sink_send()` as raw bytes
+/// 
+/// * `ALASS_SAMPLE_U8`: Unsigned 8-bit, centered at 128.
+/// * `ALASS_SAMPLE_I16`: Signed 16-bit little-endian. (default)
+/// * `ALASS_SAMPLE_I32`: Signed 32-bit little-endian.
+/// * `ALASS_SAMPLE_F32`: 32-bit little-endian float within [-1.0, 1.0].
+/// 
+/// The typed `alass_audio_sink_send_*()` functions ignore this setting.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_format_set_sample_format(format: *mut AudioFormat, sample_format: SampleFormatCode) -> ResultCode {
+    if format.is_null() {
+        error!("Invalid parameter: AudioFormat pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let sample_format = if sample_format == ALASS_SAMPLE_U8 {
+        SampleFormat::U8
+    } else if sample_format == ALASS_SAMPLE_I16 {
+        SampleFormat::I16
+    } else if sample_format == ALASS_SAMPLE_I32 {
+        SampleFormat::I32
+    } else if sample_format == ALASS_SAMPLE_F32 {
+        SampleFormat::F32
+    } else {
+        error!("Invalid parameter: unknown sample format (sample_format={})", sample_format);
+        return ALASS_INVALID_PARAMS;
+    };
+
+    let f = from_ptr(format);
+    update_format(f, AudioFormat { sample_format, ..f.clone() })
+}
+
+///
+/// Deallocates audio format descriptor
+/// 
+#[catch_panic]
+#[no_mangle]
+pub extern "C" fn alass_audio_format_free(format: *mut AudioFormat) {
+    if !format.is_null() {
+        drop(from_ptr_owned(format));
+    }
+}
+
+fn update_format(format: &mut AudioFormat, updated: AudioFormat) -> ResultCode {
+    match updated.validate() {
+        Ok(()) => {
+            *format = updated;
+            ALASS_SUCCESS
+        },
+        Err(e) => {
+            error!("Invalid parameter: {}", e);
+            ALASS_INVALID_PARAMS
+        }
+    }
+}
@@ -4,12 +4,19 @@ use crate::util::*;
 use crate::result_codes::*;
 use crate::catch_panic;
 
-use alass_util::{AudioSink, AudioSinkError};
+use alass_util::{AudioSink, AudioSinkError, AudioSinkState, AudioFormat, VadConfig, VadRate, Sample};
 
 use std::ptr;
+use std::convert::TryFrom;
+use std::os::raw::c_char;
 
 use log::error;
 
+pub type AudioSinkStateCode = u32;
+
+#[no_mangle] pub static ALASS_SINK_STATE_OPEN:   AudioSinkStateCode = 0;
+#[no_mangle] pub static ALASS_SINK_STATE_CLOSED: AudioSinkStateCode = 1;
+
 ///
 /// Allocates a new audio sink ready to receive audio samples.
 /// 
@@ -20,8 +27,73 @@ pub extern "C" fn alass_audio_sink_new() -> *mut AudioSink {
 }
 
 ///
-/// Send audio samples to given sink. Samples should be 8kHz 16-bit signed
-/// little-endian mono.
+/// Allocates a new audio sink accepting audio in the given format (see `alass_audio_format_new()`).
+/// Returns null if the format or VAD rate is unsupported.
+/// 
+/// Multi-channel audio is mixed down to mono and resampled on the fly to `vad_rate`, the
+/// rate at which voice-activity is detected (8000, 16000, or 32000). Higher rates take more
+/// of the speech spectrum into account at the expense of speed. Input sample rates from
+/// 4kHz to 384kHz are supported.
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_new_with_format(format: *mut AudioFormat, vad_rate: u32) -> *mut AudioSink {
+    if format.is_null() {
+        error!("Invalid parameter: AudioFormat pointer is null");
+        return ptr::null_mut();
+    }
+
+    let format = &*from_ptr(format);
+    let sink = VadRate::try_from(vad_rate)
+        .and_then(|vad_rate| AudioSink::with_format(format, vad_rate));
+    match sink {
+        Ok(sink) => to_ptr(sink),
+        Err(e) => {
+            error!("Invalid parameter: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+///
+/// Allocates a new audio sink accepting audio in the given format (see `alass_audio_format_new()`)
+/// and detecting voice-activity with the given settings (see `alass_vad_config_new()`). Returns
+/// null if the format is unsupported.
+/// 
+/// Audio is mixed down and resampled to the configured VAD rate as with
+/// `alass_audio_sink_new_with_format()`.
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_new_with_config(format: *mut AudioFormat, config: *mut VadConfig) -> *mut AudioSink {
+    if format.is_null() {
+        error!("Invalid parameter: AudioFormat pointer is null");
+        return ptr::null_mut();
+    } else if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ptr::null_mut();
+    }
+
+    let format = &*from_ptr(format);
+    let config = *from_ptr(config);
+    match AudioSink::with_config(format, config) {
+        Ok(sink) => to_ptr(sink),
+        Err(e) => {
+            error!("Invalid parameter: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+///
+/// Send raw audio samples to given sink. Samples are decoded according to the sink's sample
+/// format (16-bit signed little-endian unless set with `alass_audio_format_set_sample_format()`)
+/// and must be at the sink's sample rate and channel count (8kHz mono unless created with
+/// `alass_audio_sink_new_with_format()`). Multi-channel frames must be interleaved and
+/// `sample_cnt` counts individual samples rather than frames or bytes.
+/// 
+/// The buffer need not be aligned. Prefer the typed `alass_audio_sink_send_*()` functions
+/// when the sample type is known at compile time.
 /// 
 #[catch_panic(ALASS_INTERNAL_ERROR)]
 #[no_mangle]
@@ -38,30 +110,275 @@ pub extern "C" fn alass_audio_sink_send(sink: *mut AudioSink, samples: *mut u8,
     }
 
     let sink = from_ptr(sink);
-    let sample_buf = unsafe { std::slice::from_raw_parts(samples as *mut i16, sample_cnt as usize) };
-    match sink.send_samples(sample_buf) {
-        Ok(()) => ALASS_SUCCESS,
+    let byte_cnt = sample_cnt as usize * sink.sample_format().bytes();
+    let bytes = unsafe { std::slice::from_raw_parts(samples as *const u8, byte_cnt) };
+    sink_result(sink.send_bytes(bytes))
+}
+
+///
+/// Declares the presentation timestamp (millis) of the next samples sent to given sink, as
+/// reported by the demuxer
+/// 
+/// The first timestamp marks the start of the computed voice-activity so that audio starting
+/// at an offset lines up with the video timeline. Streams without timestamps start at zero.
+/// Gaps longer than a VAD frame (e.g. after seeking) are treated as non-speech without being
+/// processed and samples overlapping audio already received are dropped.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_set_timestamp(sink: *mut AudioSink, pts_millis: i64) -> ResultCode {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    sink_result(from_ptr(sink).set_timestamp(pts_millis))
+}
+
+///
+/// Send interleaved signed 16-bit samples to given sink (see `alass_audio_sink_send()`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_send_i16(sink: *mut AudioSink, samples: *const i16, sample_cnt: i64) -> ResultCode {
+    send_typed(sink, samples, sample_cnt)
+}
+
+///
+/// Send interleaved signed 32-bit samples to given sink (see `alass_audio_sink_send()`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_send_i32(sink: *mut AudioSink, samples: *const i32, sample_cnt: i64) -> ResultCode {
+    send_typed(sink, samples, sample_cnt)
+}
+
+///
+/// Send interleaved float samples within [-1.0, 1.0] to given sink (see `alass_audio_sink_send()`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_send_f32(sink: *mut AudioSink, samples: *const f32, sample_cnt: i64) -> ResultCode {
+    send_typed(sink, samples, sample_cnt)
+}
+
+///
+/// Send interleaved unsigned 8-bit samples to given sink (see `alass_audio_sink_send()`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_send_u8(sink: *mut AudioSink, samples: *const u8, sample_cnt: i64) -> ResultCode {
+    send_typed(sink, samples, sample_cnt)
+}
+
+///
+/// Send planar signed 16-bit samples to given sink. `planes` must point to one buffer per
+/// channel, each holding `frame_cnt` samples.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_send_planar_i16(sink: *mut AudioSink, planes: *const *const i16, frame_cnt: i64) -> ResultCode {
+    send_planar(sink, planes, frame_cnt)
+}
+
+///
+/// Send planar signed 32-bit samples to given sink (see `alass_audio_sink_send_planar_i16()`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_send_planar_i32(sink: *mut AudioSink, planes: *const *const i32, frame_cnt: i64) -> ResultCode {
+    send_planar(sink, planes, frame_cnt)
+}
+
+///
+/// Send planar float samples to given sink (see `alass_audio_sink_send_planar_i16()`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_send_planar_f32(sink: *mut AudioSink, planes: *const *const f32, frame_cnt: i64) -> ResultCode {
+    send_planar(sink, planes, frame_cnt)
+}
+
+///
+/// Send planar unsigned 8-bit samples to given sink (see `alass_audio_sink_send_planar_i16()`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_send_planar_u8(sink: *mut AudioSink, planes: *const *const u8, frame_cnt: i64) -> ResultCode {
+    send_planar(sink, planes, frame_cnt)
+}
+
+///
+/// Closes a given audio sink. Once a sink is closed it can no longer receive additional samples.
+/// 
+#[catch_panic]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_close(sink: *mut AudioSink) {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return;
+    }
+
+    let _ = from_ptr(sink).close();
+}
+
+///
+/// Returns the state of a given audio sink
+/// 
+/// * `ALASS_SINK_STATE_OPEN`: The sink accepts samples.
+/// 
+/// * `ALASS_SINK_STATE_CLOSED`: The sink was closed, either explicitly with
+///    `alass_audio_sink_close()` or by `alass_voice_activity_compute()`, and no longer
+///    accepts samples until reset (see `alass_audio_sink_reset()`).
+/// 
+/// Returns `ALASS_SINK_STATE_CLOSED` if `sink` is null.
+/// 
+#[catch_panic(ALASS_SINK_STATE_CLOSED)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_state(sink: *mut AudioSink) -> AudioSinkStateCode {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return ALASS_SINK_STATE_CLOSED;
+    }
+
+    match from_ptr(sink).state() {
+        AudioSinkState::Open => ALASS_SINK_STATE_OPEN,
+        AudioSinkState::Closed => ALASS_SINK_STATE_CLOSED
+    }
+}
+
+///
+/// Returns a given audio sink to its initial open state, discarding all samples and
+/// voice-activity data received so far, so that it can be reused for another stream with
+/// the same format and settings. Statistics are reset to zero.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_reset(sink: *mut AudioSink) -> ResultCode {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    from_ptr(sink).reset();
+    ALASS_SUCCESS
+}
+
+///
+/// Starts capturing the audio and voice activity processed by a given audio sink to files
+/// in directory `dir`, so that problems can be reproduced from data sent in by users
+/// 
+/// Files are named after a capture session, which is logged, so that sinks and runs do not
+/// overwrite each other's. The resulting voice activity is saved along with the detector
+/// settings when the sink is closed (see `alass_voice_activity_load()`). Errors writing
+/// files are logged and stop the capture without affecting the sink.
+/// 
+/// Returns `ALASS_WRITE_ERROR` if files cannot be created in `dir`, or `ALASS_SINK_CLOSED`
+/// if the sink has been closed.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_start_capture(sink: *mut AudioSink, dir: *const c_char) -> ResultCode {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let dir_str = from_cstring(dir);
+    if dir_str.is_none() {
+        error!("Invalid parameter: directory is invalid");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    match from_ptr(sink).start_capture(&dir_str.unwrap()) {
+        Ok(_) => ALASS_SUCCESS,
         Err(e) => {
             error!("{}", e);
             match e {
                 AudioSinkError::SinkClosed => ALASS_SINK_CLOSED,
-                _ => ALASS_INTERNAL_ERROR
+                _ => ALASS_WRITE_ERROR
             }
         }
     }
 }
 
 ///
-/// Closes a given audio sink. Once a sink is closed it can no longer receive additional samples.
+/// Stops capturing audio and voice activity (see `alass_audio_sink_start_capture()`),
+/// flushing files. Voice activity is only saved if the sink was closed while capturing.
 /// 
-#[catch_panic]
+#[catch_panic(ALASS_INTERNAL_ERROR)]
 #[no_mangle]
-pub extern "C" fn alass_audio_sink_close(sink: *mut AudioSink) {
+pub extern "C" fn alass_audio_sink_stop_capture(sink: *mut AudioSink) -> ResultCode {
     if sink.is_null() {
         error!("Invalid parameter: AudioSink pointer is null");
+        return ALASS_INVALID_PARAMS;
     }
 
-    let _ = from_ptr(sink).close();
+    from_ptr(sink).stop_capture();
+    ALASS_SUCCESS
+}
+
+///
+/// Returns the number of individual samples sent to a given audio sink, counting each
+/// channel of a frame. Returns zero if `sink` is null.
+/// 
+#[catch_panic(0)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_samples_received(sink: *mut AudioSink) -> i64 {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return 0;
+    }
+
+    from_ptr(sink).samples_received() as i64
+}
+
+///
+/// Returns the number of VAD frames processed by a given audio sink so far, excluding gaps
+/// in the stream (see `alass_audio_sink_set_timestamp()`). Samples short of a whole frame are
+/// processed once more samples arrive or the sink is closed. Returns zero if `sink` is null.
+/// 
+#[catch_panic(0)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_chunks_processed(sink: *mut AudioSink) -> i64 {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return 0;
+    }
+
+    from_ptr(sink).chunks_processed() as i64
+}
+
+///
+/// Returns the duration of audio processed by a given audio sink so far (millis), excluding
+/// gaps in the stream (see `alass_audio_sink_chunks_processed()`). Returns zero if `sink` is
+/// null.
+/// 
+#[catch_panic(0)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_duration_processed(sink: *mut AudioSink) -> i64 {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return 0;
+    }
+
+    from_ptr(sink).duration_processed() as i64
+}
+
+///
+/// Returns the fraction of VAD frames processed by a given audio sink so far that were
+/// detected as speech, between zero and one. Returns zero if no frames have been processed
+/// or `sink` is null.
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_audio_sink_speech_ratio(sink: *mut AudioSink) -> f64 {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return 0.0;
+    }
+
+    from_ptr(sink).speech_ratio()
 }
 
 ///
@@ -74,3 +391,64 @@ pub extern "C" fn alass_audio_sink_free(sink: *mut AudioSink) {
         drop(from_ptr_owned(sink));
     }
 }
+
+fn send_typed<S: Sample>(sink: *mut AudioSink, samples: *const S, sample_cnt: i64) -> ResultCode {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return ALASS_INVALID_PARAMS;
+    } else if samples.is_null() {
+        error!("Invalid parameter: sample buffer pointer is null");
+        return ALASS_INVALID_PARAMS;
+    } else if samples.align_offset(std::mem::align_of::<S>()) != 0 {
+        error!("Invalid parameter: sample buffer pointer is misaligned");
+        return ALASS_INVALID_PARAMS;
+    } else if sample_cnt < 0 {
+        error!("Invalid parameter: sample count is negative");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let samples = unsafe { std::slice::from_raw_parts(samples, sample_cnt as usize) };
+    sink_result(from_ptr(sink).send(samples))
+}
+
+fn send_planar<S: Sample>(sink: *mut AudioSink, planes: *const *const S, frame_cnt: i64) -> ResultCode {
+    if sink.is_null() {
+        error!("Invalid parameter: AudioSink pointer is null");
+        return ALASS_INVALID_PARAMS;
+    } else if planes.is_null() {
+        error!("Invalid parameter: plane array pointer is null");
+        return ALASS_INVALID_PARAMS;
+    } else if frame_cnt < 0 {
+        error!("Invalid parameter: frame count is negative");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let sink = from_ptr(sink);
+    let plane_ptrs = unsafe { std::slice::from_raw_parts(planes, sink.channels()) };
+    if plane_ptrs.iter().any(|p| p.is_null()) {
+        error!("Invalid parameter: plane pointer is null");
+        return ALASS_INVALID_PARAMS;
+    } else if plane_ptrs.iter().any(|p| p.align_offset(std::mem::align_of::<S>()) != 0) {
+        error!("Invalid parameter: plane pointer is misaligned");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let planes: Vec<&[S]> = plane_ptrs.iter()
+        .map(|p| unsafe { std::slice::from_raw_parts(*p, frame_cnt as usize) })
+        .collect();
+    sink_result(sink.send_planar(&planes))
+}
+
+fn sink_result(result: Result<(), AudioSinkError>) -> ResultCode {
+    match result {
+        Ok(()) => ALASS_SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            match e {
+                AudioSinkError::SinkClosed => ALASS_SINK_CLOSED,
+                AudioSinkError::InvalidFormat { .. } => ALASS_INVALID_PARAMS,
+                _ => ALASS_INTERNAL_ERROR
+            }
+        }
+    }
+}
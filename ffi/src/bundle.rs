@@ -0,0 +1,51 @@
+extern crate alass_util;
+
+use crate::util::*;
+use crate::result_codes::*;
+use crate::catch_panic;
+
+use alass_util::{replay_bundle, BundleError::*};
+
+use std::os::raw::c_char;
+
+use log::{error, info};
+
+///
+/// Re-runs a sync recorded to disk (see `alass_options_set_record_bundle()`) with the
+/// recorded subtitle, reference and options, logging any differences from the recorded
+/// outcome
+/// 
+/// Returns `ALASS_SUCCESS` if the replay reproduced the recorded outcome, or
+/// `ALASS_REPLAY_MISMATCH` if the outcome or any subtitle line differs. Syncs limited by a
+/// time budget may degrade differently on another machine and so may not be reproduced.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_replay_bundle(bundle_path: *const c_char) -> ResultCode {
+    let bundle_path_str = from_cstring(bundle_path);
+    if bundle_path_str.is_none() {
+        error!("Invalid parameter: bundle_path is invalid");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    match replay_bundle(&bundle_path_str.unwrap()) {
+        Ok(replay) if replay.is_identical() => {
+            info!("{}", replay);
+            ALASS_SUCCESS
+        },
+        Ok(replay) => {
+            error!("{}", replay);
+            ALASS_REPLAY_MISMATCH
+        },
+        Err(e) => {
+            error!("{}", e);
+            match e {
+                FileNotFound { .. } => ALASS_FILE_DOES_NOT_EXIST,
+                ReadError { .. } => ALASS_READ_ERROR,
+                DeserializeError { .. } => ALASS_PARSE_ERROR,
+                SerializeError { .. } => ALASS_SERIALIZE_ERROR,
+                WriteError { .. } => ALASS_WRITE_ERROR
+            }
+        }
+    }
+}
@@ -4,26 +4,39 @@ extern crate alass_ffi_macros;
 mod util;
 mod result_codes;
 mod options;
+mod audio_format;
 mod audio_sink;
+mod vad_config;
 mod timespans;
 mod voice_activity;
+mod report;
+mod tuning;
+mod ranking;
 mod logging;
+mod bundle;
 
 pub use util::*;
 pub use result_codes::*;
 pub use options::*;
+pub use audio_format::*;
 pub use audio_sink::*;
+pub use vad_config::*;
 pub use timespans::*;
 pub use voice_activity::*;
+pub use report::*;
+pub use tuning::*;
+pub use ranking::*;
 pub use logging::alass_log_config;
+pub use bundle::*;
 
 use alass_util::{sync, is_format_supported};
-use alass_util::{TimeSpans, SyncOptions, SyncError, SyncError::*};
+use alass_util::{TimeSpans, SyncOptions, SyncReport, SyncError, SyncError::*};
 
 use alass_ffi_macros::catch_panic;
 
 use log::error;
 
+use std::ptr;
 use std::os::raw::c_char;
 
 ///
@@ -56,6 +69,29 @@ pub extern "C" fn alass_sync(
         options: *mut SyncOptions)
     -> ResultCode {
 
+    alass_sync_with_report(sub_path_in, sub_path_out, ref_spans, ref_fps, sub_encoding, options, ptr::null_mut())
+}
+
+///
+/// Performs `alass` subtitle synchronization (see `alass_sync()`), populating the given
+/// report with details of the completed sync.
+/// 
+/// * `report`: Report to be populated upon success (see `alass_sync_report_new()`), or upon
+///    `ALASS_LOW_CONFIDENCE` so that the confidence can be inspected. May be null if no
+///    report is needed.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_sync_with_report(
+        sub_path_in: *const c_char,
+        sub_path_out: *const c_char,
+        ref_spans: *mut TimeSpans,
+        ref_fps: f64,
+        sub_encoding: *const c_char,
+        options: *mut SyncOptions,
+        report: *mut SyncReport)
+    -> ResultCode {
+
     if ref_spans.is_null() {
         error!("Invalid parameter: ref_spans is null");
         return ALASS_INVALID_PARAMS;
@@ -80,10 +116,19 @@ pub extern "C" fn alass_sync(
 
     let ref_spans = from_ptr(ref_spans);
     match sync(&sub_path_in_str.unwrap(), &sub_path_out_str.unwrap(), ref_spans, ref_fps, sub_encoding_str, options)    {
-        Ok(_) => ALASS_SUCCESS,
+        Ok(r) => {
+            if !report.is_null() {
+                *from_ptr(report) = r;
+            }
+            ALASS_SUCCESS
+        },
         Err(e) => {
             error!("{}", e);
-            sync_error_code(&e)
+            let code = sync_error_code(&e);
+            if let (LowConfidence { report: r, .. }, false) = (e, report.is_null()) {
+                *from_ptr(report) = r;
+            }
+            code
         }
     }
 }
@@ -123,6 +168,8 @@ fn sync_error_code(e: &SyncError) -> ResultCode {
         ParseError { .. }        => ALASS_PARSE_ERROR,
         WriteError { .. }        => ALASS_WRITE_ERROR,
         SerializeError { .. }    => ALASS_SERIALIZE_ERROR,
+        LowConfidence { .. }     => ALASS_LOW_CONFIDENCE,
+        UnusableReference { .. } => ALASS_UNUSABLE_REFERENCE,
         InternalError { .. }     => ALASS_INTERNAL_ERROR
     }
 }
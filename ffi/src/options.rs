@@ -4,6 +4,8 @@ use crate::catch_panic;
 use crate::SyncOptions;
 
 use std::ptr;
+use std::os::raw::c_char;
+use std::time::Duration;
 
 use log::*;
 
@@ -77,7 +79,6 @@ pub extern "C" fn alass_options_set_split_penalty(options: *mut SyncOptions, val
     }
 
     let o = from_ptr(options);
-    let value = value;
     if value > 0.0 && value <= 1000.0 {
         o.split_penalty = value;
         ALASS_SUCCESS
@@ -87,6 +88,28 @@ pub extern "C" fn alass_options_set_split_penalty(options: *mut SyncOptions, val
     }
 }
 
+///
+/// Whether to choose the `alass` "split_penalty" parameter automatically
+/// 
+/// When true, several candidate penalties are tried and the one giving the best balance
+/// between alignment score and number of splits is used, ignoring the value set with
+/// `alass_options_set_split_penalty()`. The chosen penalty can be retrieved with
+/// `alass_sync_report_split_penalty()`. This makes split-mode syncing several times
+/// slower. (default `false`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_options_set_split_penalty_auto(options: *mut SyncOptions, value: bool) -> ResultCode {
+    if options.is_null() {
+        error!("Invalid parameter: SyncOptions pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let o = from_ptr(options);
+    o.split_penalty_auto = value;
+    ALASS_SUCCESS
+}
+
 ///
 /// Sets the `alass` "speed_optimization" parameter
 /// 
@@ -136,6 +159,112 @@ pub extern "C" fn alass_options_set_framerate_correction(options: *mut SyncOptio
     ALASS_SUCCESS
 }
 
+///
+/// Sets the maximum time to spend on a sync (millis)
+/// 
+/// The sync is gracefully degraded as the budget is spent by skipping framerate and split
+/// penalty candidates, raising `speed_optimization`, and finally falling back to a constant
+/// shift. The budget is checked between alignments, which cannot be interrupted once started,
+/// so it may be exceeded by the duration of one alignment. Use `alass_sync_with_report()` to
+/// learn which measures were taken. Set to zero to disable the time budget. (default `0`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_options_set_time_budget(options: *mut SyncOptions, value: i64) -> ResultCode {
+    if options.is_null() {
+        error!("Invalid parameter: SyncOptions pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let o = from_ptr(options);
+
+    if value > 0 {
+        o.time_budget = Some(Duration::from_millis(value as u64));
+        ALASS_SUCCESS
+    } else if value == 0 {
+        o.time_budget = None;
+        ALASS_SUCCESS
+    } else {
+        error!("Invalid parameter: 'time_budget' cannot be negative (value={})", value);
+        ALASS_INVALID_PARAMS
+    }
+}
+
+///
+/// Sets the minimum alignment confidence required to write synced subtitles
+/// 
+/// Confidence ranges from zero (no better than a random shift) to one (perfect match). When
+/// the alignment falls below this threshold, `alass_sync()` leaves the output untouched and
+/// returns `ALASS_LOW_CONFIDENCE`. This guards against syncing to the wrong movie or to audio
+/// without speech. Set to zero to always write output. (default `0`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_options_set_min_confidence(options: *mut SyncOptions, value: f64) -> ResultCode {
+    if options.is_null() {
+        error!("Invalid parameter: SyncOptions pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let o = from_ptr(options);
+    if value > 0.0 && value <= 1.0 {
+        o.min_confidence = Some(value);
+        ALASS_SUCCESS
+    } else if value == 0.0 {
+        o.min_confidence = None;
+        ALASS_SUCCESS
+    } else {
+        error!("Invalid parameter: 'min_confidence' should be in the range [0, 1]. (value={})", value);
+        ALASS_INVALID_PARAMS
+    }
+}
+
+///
+/// Whether to reject reference timespans which are unsuitable for alignment
+/// 
+/// When true, `alass_sync()` returns `ALASS_UNUSABLE_REFERENCE` without writing output if the
+/// reference is empty, too sparse or mostly speech (see `alass_timespans_diagnose()`).
+/// Disable to sync against references known to be unusually shaped. (default `true`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_options_set_reject_unusable_reference(options: *mut SyncOptions, value: bool) -> ResultCode {
+    if options.is_null() {
+        error!("Invalid parameter: SyncOptions pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let o = from_ptr(options);
+    o.reject_unusable_reference = value;
+    ALASS_SUCCESS
+}
+
+///
+/// Sets the path to which a bundle reproducing each sync is recorded (useful for bug reports)
+/// 
+/// The bundle holds the input subtitle, reference timespans, options and outcome of the
+/// sync, so that it can be re-run with `alass_replay_bundle()`. Any existing file is
+/// overwritten. Failing to record is logged without affecting the sync. Set to null to stop
+/// recording. (default null)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_options_set_record_bundle(options: *mut SyncOptions, path: *const c_char) -> ResultCode {
+    if options.is_null() {
+        error!("Invalid parameter: SyncOptions pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let path_str = from_cstring(path);
+    if !path.is_null() && path_str.is_none() {
+        error!("Invalid parameter: path is invalid");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    from_ptr(options).record_bundle = path_str;
+    ALASS_SUCCESS
+}
+
 ///
 /// Logs the values of the given options instance (useful for debugging)
 /// 
@@ -152,8 +281,22 @@ pub extern "C" fn alass_options_log(options: *mut SyncOptions) {
         Some(v) => format!("{}", v),
         None => String::from("false")
     };
-    info!("SyncOptions(interval={}, split_mode={}, split_penalty={}, speed_optimization={}, framerate_correction={})",
-        o.interval, o.split_mode, o.split_penalty, speed_opt, o.framerate_correction);
+    let time_budget = match o.time_budget {
+        Some(v) => format!("{}ms", v.as_millis()),
+        None => String::from("none")
+    };
+    let split_penalty = if o.split_penalty_auto {
+        String::from("auto")
+    } else {
+        format!("{}", o.split_penalty)
+    };
+    let min_confidence = match o.min_confidence {
+        Some(v) => format!("{}", v),
+        None => String::from("none")
+    };
+    let record_bundle = o.record_bundle.as_deref().unwrap_or("none");
+    info!("SyncOptions(interval={}, split_mode={}, split_penalty={}, speed_optimization={}, framerate_correction={}, time_budget={}, min_confidence={}, reject_unusable_reference={}, record_bundle={})",
+        o.interval, o.split_mode, split_penalty, speed_opt, o.framerate_correction, time_budget, min_confidence, o.reject_unusable_reference, record_bundle);
 }
 
 ///
@@ -0,0 +1,197 @@
+use crate::util::*;
+use crate::result_codes::*;
+use crate::catch_panic;
+use crate::sync_error_code;
+
+use alass_util::{rank_candidates, CandidateRanking, CandidateScore, TimeSpans, SyncOptions};
+
+use std::ptr;
+use std::os::raw::c_char;
+
+use log::error;
+
+///
+/// Scores each candidate subtitle file against the reference timespans and ranks them from
+/// best to worst fit without writing anything to disk. Returns null if parameters are invalid.
+/// 
+/// Candidates are ordered by alignment confidence. Candidates which could not be read or
+/// parsed are placed last (see `alass_ranking_result_code()`).
+/// 
+/// * `sub_paths`: Array of paths to the candidate subtitle files.
+/// 
+/// * `sub_path_cnt`: Number of paths in `sub_paths`.
+/// 
+/// * `ref_spans`: Reference timespans to use for alignment.
+/// 
+/// * `ref_fps`: Framerate of the reference video file (used for framerate correction).
+/// 
+/// * `sub_encoding`: The IANA charset encoding of the subtitle files. If 'auto' is given (or
+///    if not specified), the encoding of each file is guessed based on its contents.
+/// 
+/// * `options`: Parameters governing alignment. The time budget, minimum confidence, and
+///    automatic split penalty options are not applied.
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn alass_rank_candidates(
+        sub_paths: *const *const c_char,
+        sub_path_cnt: usize,
+        ref_spans: *mut TimeSpans,
+        ref_fps: f64,
+        sub_encoding: *const c_char,
+        options: *mut SyncOptions)
+    -> *mut CandidateRanking {
+
+    if ref_spans.is_null() {
+        error!("Invalid parameter: ref_spans is null");
+        return ptr::null_mut();
+    } else if sub_paths.is_null() {
+        error!("Invalid parameter: sub_paths is null");
+        return ptr::null_mut();
+    }
+
+    let sub_path_ptrs = unsafe { std::slice::from_raw_parts(sub_paths, sub_path_cnt) };
+    let sub_path_strs: Option<Vec<String>> = sub_path_ptrs.iter().map(|&p| from_cstring(p)).collect();
+    let sub_path_strs = match sub_path_strs {
+        Some(v) => v,
+        None => {
+            error!("Invalid parameter: sub_paths contains an invalid path");
+            return ptr::null_mut();
+        }
+    };
+    let sub_path_refs: Vec<&str> = sub_path_strs.iter().map(String::as_str).collect();
+
+    let sub_encoding_str = from_cstring(sub_encoding);
+
+    let dflt_opts = SyncOptions::default();
+    let options = from_ptr_safe(options).unwrap_or(&dflt_opts);
+
+    let ref_spans = from_ptr(ref_spans);
+    to_ptr(rank_candidates(&sub_path_refs, ref_spans, ref_fps, sub_encoding_str, options))
+}
+
+///
+/// Returns the number of ranked candidates
+/// 
+#[catch_panic(0)]
+#[no_mangle]
+pub extern "C" fn alass_ranking_count(ranking: *mut CandidateRanking) -> i64 {
+    if ranking.is_null() {
+        error!("Invalid parameter: CandidateRanking pointer is null");
+        return 0;
+    }
+
+    from_ptr(ranking).0.len() as i64
+}
+
+///
+/// Returns the position within the `sub_paths` array given to `alass_rank_candidates()` of
+/// the candidate at the given rank (zero is best). Returns -1 if the rank is out of bounds.
+/// 
+#[catch_panic(-1)]
+#[no_mangle]
+pub extern "C" fn alass_ranking_candidate_index(ranking: *mut CandidateRanking, rank: i64) -> i64 {
+    if ranking.is_null() {
+        error!("Invalid parameter: CandidateRanking pointer is null");
+        return -1;
+    }
+
+    match ranked(ranking, rank) {
+        Some(c) => c.index as i64,
+        None => -1
+    }
+}
+
+///
+/// Returns `ALASS_SUCCESS` if the candidate at the given rank was scored, otherwise the
+/// result code describing why it could not be
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_ranking_result_code(ranking: *mut CandidateRanking, rank: i64) -> ResultCode {
+    if ranking.is_null() {
+        error!("Invalid parameter: CandidateRanking pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    match ranked(ranking, rank) {
+        Some(c) => match &c.score {
+            Ok(_) => ALASS_SUCCESS,
+            Err(e) => sync_error_code(e)
+        },
+        None => ALASS_INVALID_PARAMS
+    }
+}
+
+///
+/// Returns the normalized alignment confidence (between 0 and 1) of the candidate at the
+/// given rank. Returns zero if the candidate could not be scored.
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_ranking_confidence(ranking: *mut CandidateRanking, rank: i64) -> f64 {
+    ranked_score(ranking, rank).map(|s| s.confidence).unwrap_or(0.0)
+}
+
+///
+/// Returns the `alass` constant-shift alignment score of the candidate at the given rank.
+/// Returns zero if the candidate could not be scored.
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_ranking_nosplit_score(ranking: *mut CandidateRanking, rank: i64) -> f64 {
+    ranked_score(ranking, rank).map(|s| s.nosplit_score).unwrap_or(0.0)
+}
+
+///
+/// Returns the `alass` split-mode alignment score of the candidate at the given rank.
+/// Returns zero if the candidate could not be scored.
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_ranking_split_score(ranking: *mut CandidateRanking, rank: i64) -> f64 {
+    ranked_score(ranking, rank).map(|s| s.split_score).unwrap_or(0.0)
+}
+
+///
+/// Returns the detected ratio between the framerate of the candidate at the given rank and
+/// the reference framerate (`1.0` unless framerate correction is enabled). Returns zero if
+/// the candidate could not be scored.
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_ranking_fps_ratio(ranking: *mut CandidateRanking, rank: i64) -> f64 {
+    ranked_score(ranking, rank).map(|s| s.fps_ratio).unwrap_or(0.0)
+}
+
+///
+/// Deallocates candidate ranking
+/// 
+#[catch_panic]
+#[no_mangle]
+pub extern "C" fn alass_ranking_free(ranking: *mut CandidateRanking) {
+    if !ranking.is_null() {
+        drop(from_ptr_owned(ranking));
+    }
+}
+
+fn ranked(ranking: *mut CandidateRanking, rank: i64) -> Option<&'static alass_util::RankedCandidate> {
+    if rank < 0 {
+        error!("Invalid parameter: rank is negative");
+        return None;
+    }
+    let candidate = from_ptr(ranking).0.get(rank as usize);
+    if candidate.is_none() {
+        error!("Invalid parameter: rank is out of bounds (rank={})", rank);
+    }
+    candidate
+}
+
+fn ranked_score(ranking: *mut CandidateRanking, rank: i64) -> Option<&'static CandidateScore> {
+    if ranking.is_null() {
+        error!("Invalid parameter: CandidateRanking pointer is null");
+        return None;
+    }
+    ranked(ranking, rank).and_then(|c| c.score.as_ref().ok())
+}
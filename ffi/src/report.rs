@@ -0,0 +1,89 @@
+use crate::util::*;
+use crate::catch_panic;
+
+use alass_util::{SyncReport, Degradation};
+
+use std::ptr;
+
+use log::error;
+
+pub type DegradationFlags = u32;
+
+#[no_mangle] pub static ALASS_DEGRADATION_NONE:                  DegradationFlags = 0;
+#[no_mangle] pub static ALASS_DEGRADATION_FRAMERATE_SKIPPED:     DegradationFlags = 1;
+#[no_mangle] pub static ALASS_DEGRADATION_SPEED_OPTIMIZATION:    DegradationFlags = 2;
+#[no_mangle] pub static ALASS_DEGRADATION_SPLIT_MODE_DISABLED:   DegradationFlags = 4;
+#[no_mangle] pub static ALASS_DEGRADATION_SPLIT_PENALTY_SKIPPED: DegradationFlags = 8;
+
+///
+/// Creates a new sync report ready to be populated by `alass_sync_with_report()`
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_sync_report_new() -> *mut SyncReport {
+    to_ptr(SyncReport::default())
+}
+
+///
+/// Returns the measures taken to keep the sync within its time budget as a combination
+/// of `ALASS_DEGRADATION_*` flags (`ALASS_DEGRADATION_NONE` if the sync ran as configured)
+/// 
+#[catch_panic(ALASS_DEGRADATION_NONE)]
+#[no_mangle]
+pub extern "C" fn alass_sync_report_degradations(report: *mut SyncReport) -> DegradationFlags {
+    if report.is_null() {
+        error!("Invalid parameter: SyncReport pointer is null");
+        return ALASS_DEGRADATION_NONE;
+    }
+
+    from_ptr(report).degradations.iter()
+        .map(|d| match d {
+            Degradation::FramerateCandidatesSkipped { .. } => ALASS_DEGRADATION_FRAMERATE_SKIPPED,
+            Degradation::SplitPenaltyCandidatesSkipped { .. } => ALASS_DEGRADATION_SPLIT_PENALTY_SKIPPED,
+            Degradation::SpeedOptimizationRaised { .. } => ALASS_DEGRADATION_SPEED_OPTIMIZATION,
+            Degradation::SplitModeDisabled => ALASS_DEGRADATION_SPLIT_MODE_DISABLED
+        })
+        .fold(ALASS_DEGRADATION_NONE, |flags, f| flags | f)
+}
+
+///
+/// Returns the split penalty used for alignment, which may have been selected automatically
+/// (see `alass_options_set_split_penalty_auto()`). Returns zero if the subtitles were shifted
+/// by a constant offset.
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_sync_report_split_penalty(report: *mut SyncReport) -> f64 {
+    if report.is_null() {
+        error!("Invalid parameter: SyncReport pointer is null");
+        return 0.0;
+    }
+
+    from_ptr(report).split_penalty.unwrap_or(0.0)
+}
+
+///
+/// Returns the normalized alignment confidence, ranging from zero (no better than a random
+/// shift) to one (perfect match with the reference)
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_sync_report_confidence(report: *mut SyncReport) -> f64 {
+    if report.is_null() {
+        error!("Invalid parameter: SyncReport pointer is null");
+        return 0.0;
+    }
+
+    from_ptr(report).confidence
+}
+
+///
+/// Deallocates sync report
+/// 
+#[catch_panic]
+#[no_mangle]
+pub extern "C" fn alass_sync_report_free(report: *mut SyncReport) {
+    if !report.is_null() {
+        drop(from_ptr_owned(report));
+    }
+}
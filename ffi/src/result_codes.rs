@@ -11,4 +11,7 @@ pub type ResultCode = u32;
 #[no_mangle] pub static ALASS_PARSE_ERROR:              ResultCode = 8;
 #[no_mangle] pub static ALASS_WRITE_ERROR:              ResultCode = 9;
 #[no_mangle] pub static ALASS_SERIALIZE_ERROR:          ResultCode = 10;
-#[no_mangle] pub static ALASS_LOG_ALREADY_CONFIGURED:   ResultCode = 11;
\ No newline at end of file
+#[no_mangle] pub static ALASS_LOG_ALREADY_CONFIGURED:   ResultCode = 11;
+#[no_mangle] pub static ALASS_LOW_CONFIDENCE:           ResultCode = 12;
+#[no_mangle] pub static ALASS_UNUSABLE_REFERENCE:       ResultCode = 13;
+#[no_mangle] pub static ALASS_REPLAY_MISMATCH:          ResultCode = 14;
\ No newline at end of file
@@ -13,7 +13,7 @@ use alass_util::TimeSpansSaveError::*;
 use subparse::timetypes::TimeSpan as SubTimeSpan;
 use subparse::timetypes::TimePoint as SubTimePoint;
 
-use log::error;
+use log::{error, info};
 
 static DEFAULT_SPANS_CAPACITY: usize = 2000;
 
@@ -23,7 +23,7 @@ static DEFAULT_SPANS_CAPACITY: usize = 2000;
 #[catch_panic(ptr::null_mut())]
 #[no_mangle]
 pub extern "C" fn alass_timespans_new() -> *mut TimeSpans {
-    to_ptr(TimeSpans(Vec::with_capacity(DEFAULT_SPANS_CAPACITY)))
+    to_ptr(TimeSpans::new(Vec::with_capacity(DEFAULT_SPANS_CAPACITY)))
 }
 
 ///
@@ -50,6 +50,61 @@ pub extern "C" fn alass_timespans_push(spans: *mut TimeSpans, start_time: i64, e
     ALASS_SUCCESS
 }
 
+///
+/// Marks a region of the timeline as known. Start and end times are in milliseconds.
+/// 
+/// Timespans are assumed to cover the whole timeline until a known region is pushed, after
+/// which everything outside of known regions is considered unknown and ignored by alignment.
+/// Use this when building a reference from select windows of audio (see
+/// `alass_sample_window_start()`). Coverage is tracked automatically by
+/// `alass_timespans_compute()`.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_timespans_push_known(spans: *mut TimeSpans, start_time: i64, end_time: i64) -> ResultCode {
+    if spans.is_null() {
+        error!("Invalid parameter: spans is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    if start_time > end_time {
+        error!("Invalid parameter: invalid timespan");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let spans = from_ptr(spans);
+    let start = SubTimePoint::from_msecs(start_time);
+    let end = SubTimePoint::from_msecs(end_time);
+    spans.push_known(SubTimeSpan::new(start, end));
+
+    ALASS_SUCCESS
+}
+
+///
+/// Chooses windows of audio to decode when decoding the whole track would take too long,
+/// returning the start time (millis) of the window at `index`, or -1 if invalid.
+/// 
+/// `count` windows of `window_millis` are spread evenly across `duration_millis`. If the
+/// windows would cover the whole duration a single window starting at zero is returned. Send
+/// each window to the audio sink preceded by `alass_audio_sink_set_timestamp()`; the
+/// resulting reference will ignore the audio in between.
+/// 
+/// Windows should be at least a minute long and cover at least a tenth of the duration for
+/// reliable results.
+/// 
+#[catch_panic(-1)]
+#[no_mangle]
+pub extern "C" fn alass_sample_window_start(duration_millis: i64, window_millis: i64, count: u32, index: u32) -> i64 {
+    let windows = sample_windows(duration_millis, window_millis, count as usize);
+    match windows.get(index as usize) {
+        Some(w) => w.start.msecs(),
+        None => {
+            error!("Invalid parameter: no such window (duration_millis={}, window_millis={}, count={}, index={})", duration_millis, window_millis, count, index);
+            -1
+        }
+    }
+}
+
 ///
 /// Computes timespans given detected voice-activity.
 /// 
@@ -66,6 +121,97 @@ pub extern "C" fn alass_timespans_compute(activity: *mut VoiceActivity) -> *mut
     to_ptr(spans)
 }
 
+///
+/// Computes timespans from the speech likelihood of detected voice-activity using threshold
+/// hysteresis. Returns null if a threshold is invalid.
+/// 
+/// Speech starts once the likelihood reaches `on_threshold` and continues until it drops below
+/// `off_threshold`. Both thresholds must lie between 0 and 1 with `off_threshold` no greater
+/// than `on_threshold`. Thresholds of 0.5 reproduce `alass_timespans_compute()`; raising them
+/// detects less speech. Allows tuning sensitivity without processing the audio again.
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_timespans_compute_hysteresis(activity: *mut VoiceActivity, on_threshold: f32, off_threshold: f32) -> *mut TimeSpans {
+    if activity.is_null() {
+        error!("Invalid parameter: voice activity pointer is null");
+        return ptr::null_mut()
+    } else if !(0.0 ..= 1.0).contains(&on_threshold) || !(0.0 ..= 1.0).contains(&off_threshold) {
+        error!("Invalid parameter: thresholds must be between 0 and 1 (on_threshold={}, off_threshold={})", on_threshold, off_threshold);
+        return ptr::null_mut()
+    } else if off_threshold > on_threshold {
+        error!("Invalid parameter: 'off_threshold' must not exceed 'on_threshold' (on_threshold={}, off_threshold={})", on_threshold, off_threshold);
+        return ptr::null_mut()
+    }
+
+    let activity = &*from_ptr(activity);
+    to_ptr(TimeSpans::from_likelihood(activity, on_threshold, off_threshold))
+}
+
+///
+/// Computes timespans from detected voice-activity, tuning thresholds and cleanup (see
+/// `alass_voice_activity_clean_ms()`) so that the lengths of speech spans and pauses match
+/// those of the subtitle to be synced (see `alass_timespans_load_subtitle()`). The chosen
+/// parameters are logged and written to `tuning` (see `alass_cleanup_tuning_new()`) unless
+/// it is null.
+/// 
+/// Only statistics of `sub_spans` are used, so the subtitle may be arbitrarily out of sync.
+/// Returns null if the subtitle has fewer than 10 lines or no pauses between them, in which
+/// case `alass_timespans_compute()` should be used instead.
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_timespans_compute_tuned(activity: *mut VoiceActivity, sub_spans: *mut TimeSpans, tuning: *mut CleanupTuning) -> *mut TimeSpans {
+    if activity.is_null() {
+        error!("Invalid parameter: voice activity pointer is null");
+        return ptr::null_mut()
+    } else if sub_spans.is_null() {
+        error!("Invalid parameter: subtitle spans pointer is null");
+        return ptr::null_mut()
+    }
+
+    let activity = &*from_ptr(activity);
+    let sub_spans = &*from_ptr(sub_spans);
+    match tune_cleanup(activity, sub_spans) {
+        Some((spans, t)) => {
+            info!("{}", t);
+            if !tuning.is_null() {
+                *from_ptr(tuning) = t;
+            }
+            to_ptr(spans)
+        },
+        None => {
+            error!("Cannot tune voice activity: too few subtitle lines or pauses (lines={})", sub_spans.spans().len());
+            ptr::null_mut()
+        }
+    }
+}
+
+///
+/// Determines whether timespans are suitable as a reference for syncing, logging statistics
+/// such as span count, coverage and longest silence
+/// 
+/// Returns `ALASS_SUCCESS` if usable or `ALASS_UNUSABLE_REFERENCE` if the timespans are empty,
+/// too sparse, or mostly speech (which `alass_sync()` refuses unless disabled with
+/// `alass_options_set_reject_unusable_reference()`).
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_timespans_diagnose(spans: *mut TimeSpans) -> ResultCode {
+    if spans.is_null() {
+        error!("Invalid parameter: spans is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let diagnosis = from_ptr(spans).diagnose();
+    info!("{}", diagnosis);
+    if diagnosis.is_usable() {
+        ALASS_SUCCESS
+    } else {
+        ALASS_UNUSABLE_REFERENCE
+    }
+}
+
 ///
 /// Saves timespans to disk with the given `filename` (see `alass_timespans_load_raw()`).
 /// 
@@ -119,6 +265,33 @@ pub extern "C" fn alass_timespans_load_raw(filename: *const c_char) -> *mut Time
     }
 }
 
+///
+/// Computes timespans from the speech in a WAVE file. Returns null if the file does not
+/// exist or cannot be read.
+/// 
+/// Integer PCM (8, 16, 24 or 32-bit) and float samples are supported at any sample rate
+/// and channel count, including files with an extensible header. Audio is mixed down and
+/// resampled as needed, and voice activity is detected with the default settings (see
+/// `alass_voice_activity_from_wav()` to change them).
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_timespans_from_wav(filename: *const c_char) -> *mut TimeSpans {
+    let filename_str = from_cstring(filename);
+    if filename_str.is_none() {
+        error!("Invalid parameter: filename is invalid");
+        return ptr::null_mut();
+    }
+
+    match WavReader::open(&filename_str.unwrap()).and_then(|wav| wav.voice_activity(VadConfig::default())) {
+        Ok(activity) => to_ptr(TimeSpans::from(&activity)),
+        Err(e) => {
+            error!("{}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
 ///
 /// Loads timespans from subtitle file. Returns null if no file exists at the given path.
 /// 
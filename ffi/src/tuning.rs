@@ -0,0 +1,133 @@
+use crate::util::*;
+use crate::catch_panic;
+
+use alass_util::CleanupTuning;
+
+use std::ptr;
+
+use log::error;
+
+///
+/// Creates a new cleanup tuning ready to be populated by `alass_timespans_compute_tuned()`
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_new() -> *mut CleanupTuning {
+    to_ptr(CleanupTuning::default())
+}
+
+///
+/// Returns the chosen speech-likelihood threshold at which speech starts (see
+/// `alass_timespans_compute_hysteresis()`)
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_on_threshold(tuning: *mut CleanupTuning) -> f32 {
+    if tuning.is_null() {
+        error!("Invalid parameter: CleanupTuning pointer is null");
+        return 0.0;
+    }
+
+    from_ptr(tuning).on_threshold
+}
+
+///
+/// Returns the chosen speech-likelihood threshold below which speech ends (see
+/// `alass_timespans_compute_hysteresis()`)
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_off_threshold(tuning: *mut CleanupTuning) -> f32 {
+    if tuning.is_null() {
+        error!("Invalid parameter: CleanupTuning pointer is null");
+        return 0.0;
+    }
+
+    from_ptr(tuning).off_threshold
+}
+
+///
+/// Returns the chosen maximum length of gaps bridged between speech spans in milliseconds
+/// (see `alass_voice_activity_clean_ms()`)
+/// 
+#[catch_panic(0)]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_max_gap_ms(tuning: *mut CleanupTuning) -> u32 {
+    if tuning.is_null() {
+        error!("Invalid parameter: CleanupTuning pointer is null");
+        return 0;
+    }
+
+    from_ptr(tuning).cleanup.max_gap_millis as u32
+}
+
+///
+/// Returns the chosen minimum length of speech spans in milliseconds (see
+/// `alass_voice_activity_clean_ms()`)
+/// 
+#[catch_panic(0)]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_min_speech_ms(tuning: *mut CleanupTuning) -> u32 {
+    if tuning.is_null() {
+        error!("Invalid parameter: CleanupTuning pointer is null");
+        return 0;
+    }
+
+    from_ptr(tuning).cleanup.min_speech_millis as u32
+}
+
+///
+/// Returns the chosen extension of speech spans before their start in milliseconds (see
+/// `alass_voice_activity_clean_ms()`)
+/// 
+#[catch_panic(0)]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_onset_ms(tuning: *mut CleanupTuning) -> u32 {
+    if tuning.is_null() {
+        error!("Invalid parameter: CleanupTuning pointer is null");
+        return 0;
+    }
+
+    from_ptr(tuning).cleanup.onset_millis as u32
+}
+
+///
+/// Returns the chosen extension of speech spans past their end in milliseconds (see
+/// `alass_voice_activity_clean_ms()`)
+/// 
+#[catch_panic(0)]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_offset_ms(tuning: *mut CleanupTuning) -> u32 {
+    if tuning.is_null() {
+        error!("Invalid parameter: CleanupTuning pointer is null");
+        return 0;
+    }
+
+    from_ptr(tuning).cleanup.offset_millis as u32
+}
+
+///
+/// Returns the dissimilarity between the span statistics of the tuned timespans and those
+/// of the subtitle. Zero if identical.
+/// 
+#[catch_panic(0.0)]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_distance(tuning: *mut CleanupTuning) -> f64 {
+    if tuning.is_null() {
+        error!("Invalid parameter: CleanupTuning pointer is null");
+        return 0.0;
+    }
+
+    from_ptr(tuning).distance
+}
+
+///
+/// Deallocates cleanup tuning
+/// 
+#[catch_panic]
+#[no_mangle]
+pub extern "C" fn alass_cleanup_tuning_free(tuning: *mut CleanupTuning) {
+    if !tuning.is_null() {
+        drop(from_ptr_owned(tuning));
+    }
+}
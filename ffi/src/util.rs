@@ -7,6 +7,7 @@ use std::os::raw::c_char;
 /// working with heap values recieved from accross an ffi boundary without
 /// dropping them when finished. Panics if pointer is `null`.
 /// 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn from_ptr<T>(value: *mut T) -> &'static mut T {
     unsafe { Box::leak(Box::from_raw(value)) }
 }
@@ -29,6 +30,7 @@ pub fn from_ptr_safe<T>(value: *mut T) -> Option<&'static T> {
 /// out of scope. Useful for deallocating heap values received from
 /// accross an ffi boundary. Panics if pointer is `null`.
 /// 
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn from_ptr_owned<T>(value: *mut T) -> Box<T> {
     unsafe { Box::from_raw(value) }
 }
@@ -47,6 +49,7 @@ pub fn to_ptr<T>(value: T) -> *mut T {
 /// instance. Will return `None` if the pointer is `null`, or if
 /// the C string is not a valid UTF-8 sequence.
 ///
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn from_cstring(s: *const c_char) -> Option<String> {
     if s.is_null() {
         None
@@ -0,0 +1,301 @@
+extern crate alass_util;
+
+use crate::util::*;
+use crate::result_codes::*;
+use crate::catch_panic;
+
+use alass_util::{VadConfig, VadBackend, VadMode, VadFrame, VadRate, BandPass};
+
+use std::ptr;
+use std::convert::TryFrom;
+
+use log::error;
+
+pub type VadBackendCode = u32;
+
+#[no_mangle] pub static ALASS_VAD_BACKEND_WEBRTC: VadBackendCode = 0;
+#[no_mangle] pub static ALASS_VAD_BACKEND_ENERGY: VadBackendCode = 1;
+
+pub type VadModeCode = u32;
+
+#[no_mangle] pub static ALASS_VAD_MODE_QUALITY:         VadModeCode = 0;
+#[no_mangle] pub static ALASS_VAD_MODE_LOW_BITRATE:     VadModeCode = 1;
+#[no_mangle] pub static ALASS_VAD_MODE_AGGRESSIVE:      VadModeCode = 2;
+#[no_mangle] pub static ALASS_VAD_MODE_VERY_AGGRESSIVE: VadModeCode = 3;
+
+///
+/// Creates a new voice-activity detector configuration initialized to default values
+/// (`ALASS_VAD_BACKEND_WEBRTC`, `ALASS_VAD_MODE_LOW_BITRATE`, 30ms frames, 8kHz, binary
+/// decisions and no preprocessing)
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_new() -> *mut VadConfig {
+    to_ptr(VadConfig::default())
+}
+
+///
+/// Sets the voice-activity detector implementation
+/// 
+/// * `ALASS_VAD_BACKEND_WEBRTC`: The WebRTC voice-activity detector. Accurate on typical
+///    film and TV soundtracks. (default)
+/// * `ALASS_VAD_BACKEND_ENERGY`: Compares frame energy against an adaptive noise floor.
+///    Several times faster, suited to embedded targets, but more easily fooled by music.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_backend(config: *mut VadConfig, backend: VadBackendCode) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let backend = if backend == ALASS_VAD_BACKEND_WEBRTC {
+        VadBackend::WebRtc
+    } else if backend == ALASS_VAD_BACKEND_ENERGY {
+        VadBackend::Energy
+    } else {
+        error!("Invalid parameter: unknown VAD backend (backend={})", backend);
+        return ALASS_INVALID_PARAMS;
+    };
+
+    from_ptr(config).backend = backend;
+    ALASS_SUCCESS
+}
+
+///
+/// Sets the sensitivity of the voice-activity detector (one of the `ALASS_VAD_MODE_*` constants,
+/// from least to most aggressive)
+/// 
+/// Less aggressive modes detect more speech at the risk of false positives on music and noise.
+/// Quiet films benefit from `ALASS_VAD_MODE_QUALITY`, dialogue-heavy programs with background
+/// music from `ALASS_VAD_MODE_AGGRESSIVE`. For the energy backend the mode sets the margin by
+/// which speech must exceed the noise floor. (default `ALASS_VAD_MODE_LOW_BITRATE`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_mode(config: *mut VadConfig, mode: VadModeCode) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let mode = if mode == ALASS_VAD_MODE_QUALITY {
+        VadMode::Quality
+    } else if mode == ALASS_VAD_MODE_LOW_BITRATE {
+        VadMode::LowBitrate
+    } else if mode == ALASS_VAD_MODE_AGGRESSIVE {
+        VadMode::Aggressive
+    } else if mode == ALASS_VAD_MODE_VERY_AGGRESSIVE {
+        VadMode::VeryAggressive
+    } else {
+        error!("Invalid parameter: unknown VAD mode (mode={})", mode);
+        return ALASS_INVALID_PARAMS;
+    };
+
+    from_ptr(config).mode = mode;
+    ALASS_SUCCESS
+}
+
+///
+/// Sets the duration of each frame classified by the voice-activity detector (10, 20, or 30ms)
+/// 
+/// Shorter frames yield finer-grained voice-activity at the expense of speed. (default `30`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_frame_millis(config: *mut VadConfig, millis: u32) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    match VadFrame::try_from(millis) {
+        Ok(frame) => {
+            from_ptr(config).frame = frame;
+            ALASS_SUCCESS
+        },
+        Err(e) => {
+            error!("Invalid parameter: {}", e);
+            ALASS_INVALID_PARAMS
+        }
+    }
+}
+
+///
+/// Sets the sample rate at which voice-activity is detected (8000, 16000, or 32000)
+/// 
+/// Higher rates take more of the speech spectrum into account at the expense of speed.
+/// (default `8000`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_rate(config: *mut VadConfig, rate: u32) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    match VadRate::try_from(rate) {
+        Ok(rate) => {
+            from_ptr(config).rate = rate;
+            ALASS_SUCCESS
+        },
+        Err(e) => {
+            error!("Invalid parameter: {}", e);
+            ALASS_INVALID_PARAMS
+        }
+    }
+}
+
+///
+/// Enables graded speech likelihood for the WebRTC backend
+/// 
+/// The WebRTC detector only makes binary decisions. When enabled each frame is classified at
+/// every `ALASS_VAD_MODE_*` sensitivity so that `alass_timespans_compute_hysteresis()` can
+/// later choose a sensitivity without processing the audio again. About four times slower.
+/// Other backends always grade their likelihood. (default `false`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_soft(config: *mut VadConfig, value: bool) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    from_ptr(config).soft = value;
+    ALASS_SUCCESS
+}
+
+///
+/// Whether to rate the likelihood of music in each frame alongside voice-activity detection
+/// 
+/// Required by `alass_voice_activity_without_music()` and `alass_voice_activity_weight_music()`
+/// to exclude or down-weight songs and scores. Adds a little processing per frame.
+/// (default `false`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_music_discrimination(config: *mut VadConfig, value: bool) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    from_ptr(config).music_discrimination = value;
+    ALASS_SUCCESS
+}
+
+///
+/// Whether to remove any constant (DC) offset from the audio before voice-activity detection
+/// 
+/// Some recordings carry an offset which inflates the energy of every frame. (default `false`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_dc_removal(config: *mut VadConfig, value: bool) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    from_ptr(config).preprocessing.dc_removal = value;
+    ALASS_SUCCESS
+}
+
+///
+/// Sets the pass band of the filter applied before voice-activity detection (Hz)
+/// 
+/// Removes frequencies outside of the band, such as mains hum, wind rumble and cymbals, which
+/// cause false positives. 200-4000Hz keeps most of the energy of speech. The high cutoff is
+/// ignored when close to half the detection rate (see `alass_vad_config_set_rate()`). Set both
+/// to zero to disable the filter. (default disabled)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_band_pass(config: *mut VadConfig, low_hz: f32, high_hz: f32) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let c = from_ptr(config);
+    if low_hz == 0.0 && high_hz == 0.0 {
+        c.preprocessing.band_pass = None;
+        ALASS_SUCCESS
+    } else if low_hz > 0.0 && low_hz < high_hz {
+        c.preprocessing.band_pass = Some(BandPass { low_hz, high_hz });
+        ALASS_SUCCESS
+    } else {
+        error!("Invalid parameter: band-pass cutoffs must be positive with 'low_hz' below 'high_hz' (low_hz={}, high_hz={})", low_hz, high_hz);
+        ALASS_INVALID_PARAMS
+    }
+}
+
+///
+/// Sets the margin (dB) by which frames must exceed the noise floor to pass the noise gate
+/// applied before voice-activity detection
+/// 
+/// Frames closer to the noise floor are silenced, suppressing steady background noise. Around
+/// 6dB is a good start. Set to zero to disable the gate. (default `0`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_noise_gate(config: *mut VadConfig, margin_db: f32) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let c = from_ptr(config);
+    if margin_db > 0.0 {
+        c.preprocessing.noise_gate = Some(margin_db);
+        ALASS_SUCCESS
+    } else if margin_db == 0.0 {
+        c.preprocessing.noise_gate = None;
+        ALASS_SUCCESS
+    } else {
+        error!("Invalid parameter: 'noise_gate' cannot be negative (value={})", margin_db);
+        ALASS_INVALID_PARAMS
+    }
+}
+
+///
+/// Sets the loudness (dBFS) to which audio is normalized before voice-activity detection
+/// 
+/// Gain follows the loudness of the last few seconds so that quiet and loud soundtracks are
+/// treated alike. Quiet audio is amplified by at most 30dB. Around -20dBFS is a good start.
+/// Set to zero to disable normalization. (default `0`)
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_set_normalization(config: *mut VadConfig, target_db: f32) -> ResultCode {
+    if config.is_null() {
+        error!("Invalid parameter: VadConfig pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let c = from_ptr(config);
+    if target_db < 0.0 {
+        c.preprocessing.normalize = Some(target_db);
+        ALASS_SUCCESS
+    } else if target_db == 0.0 {
+        c.preprocessing.normalize = None;
+        ALASS_SUCCESS
+    } else {
+        error!("Invalid parameter: 'normalization' must lie below 0dBFS (value={})", target_db);
+        ALASS_INVALID_PARAMS
+    }
+}
+
+///
+/// Deallocates voice-activity detector configuration
+/// 
+#[catch_panic]
+#[no_mangle]
+pub extern "C" fn alass_vad_config_free(config: *mut VadConfig) {
+    if !config.is_null() {
+        drop(from_ptr_owned(config));
+    }
+}
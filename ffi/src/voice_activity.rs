@@ -1,16 +1,19 @@
 extern crate alass_util;
 
 use crate::util::*;
+use crate::result_codes::*;
 use crate::catch_panic;
 
-use alass_util::{AudioSink, VoiceActivity};
+use alass_util::{AudioSink, AudioFormat, Cleanup, VadConfig, VoiceActivity, VoiceActivitySaveError, WavReader, parallel_voice_activity_bytes};
 
 use std::ptr;
+use std::os::raw::c_char;
 
 use log::error;
 
 ///
-/// Computes voice activity given an audio sink containing sample data.
+/// Computes voice activity given an audio sink containing sample data. The sink is closed
+/// if it has not been already (see `alass_audio_sink_state()`).
 /// 
 #[catch_panic(ptr::null_mut())]
 #[no_mangle]
@@ -25,6 +28,81 @@ pub extern "C" fn alass_voice_activity_compute(sink: *mut AudioSink) -> *mut Voi
     to_ptr(activity)
 }
 
+///
+/// Computes voice activity of a whole track of decoded audio held in memory using up to
+/// `threads` threads. Returns null if the format is unsupported or the buffer does not hold a
+/// whole number of frames.
+/// 
+/// Samples are decoded according to the format's sample format (see
+/// `alass_audio_format_set_sample_format()`), multi-channel frames must be interleaved and
+/// `sample_cnt` counts individual samples. The buffer need not be aligned. Voice activity is
+/// detected with the given settings (see `alass_vad_config_new()`), or the defaults if
+/// `config` is null.
+/// 
+/// The audio is split into segments of two minutes processed independently. Each segment is
+/// processed along with the six seconds preceding it so that detector state has settled by
+/// the segment boundary. Results closely match those of an audio sink and do not depend on
+/// the number of threads.
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn alass_voice_activity_compute_parallel(format: *mut AudioFormat, config: *mut VadConfig, samples: *const u8, sample_cnt: i64, threads: u32) -> *mut VoiceActivity {
+    if format.is_null() {
+        error!("Invalid parameter: AudioFormat pointer is null");
+        return ptr::null_mut()
+    } else if samples.is_null() {
+        error!("Invalid parameter: sample buffer pointer is null");
+        return ptr::null_mut()
+    } else if sample_cnt < 0 {
+        error!("Invalid parameter: sample count is negative");
+        return ptr::null_mut()
+    }
+
+    let format = &*from_ptr(format);
+    let config = if config.is_null() { VadConfig::default() } else { *from_ptr(config) };
+    let byte_cnt = sample_cnt as usize * format.sample_format.bytes();
+    let bytes = unsafe { std::slice::from_raw_parts(samples, byte_cnt) };
+    match parallel_voice_activity_bytes(format, config, bytes, threads as usize) {
+        Ok(activity) => to_ptr(activity),
+        Err(e) => {
+            error!("{}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+///
+/// Computes voice activity of the audio in a WAVE file (see `alass_timespans_from_wav()`)
+/// with the given settings (see `alass_vad_config_new()`), or the defaults if `config` is
+/// null. Returns null if the file does not exist or cannot be read. The filename is recorded
+/// as the source of the voice activity (see `alass_voice_activity_save()`).
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_voice_activity_from_wav(filename: *const c_char, config: *mut VadConfig) -> *mut VoiceActivity {
+    let filename_str = from_cstring(filename);
+    if filename_str.is_none() {
+        error!("Invalid parameter: filename is invalid");
+        return ptr::null_mut();
+    }
+
+    let filename_str = filename_str.unwrap();
+    let config = if config.is_null() { VadConfig::default() } else { *from_ptr(config) };
+    match WavReader::open(&filename_str).and_then(|wav| wav.voice_activity(config)) {
+        Ok(mut activity) => {
+            if let Some(source) = activity.source.as_mut() {
+                source.name = Some(filename_str);
+            }
+            to_ptr(activity)
+        },
+        Err(e) => {
+            error!("{}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
 ///
 /// [EXPERIMENTAL] Cleans voice-activity data
 /// 
@@ -35,7 +113,7 @@ pub extern "C" fn alass_voice_activity_compute(sink: *mut AudioSink) -> *mut Voi
 /// 
 /// The `opening_radius` and `closing_radius` parameters represent the kernel radii
 /// of the mathematical morphological operators. Each radius determines a window
-/// of size `(2r+1)*CHUNK_MILLIS` milliseconds. Any errant spans smaller than this
+/// of size `(2r+1)` frames (see `alass_vad_config_set_frame_millis()`). Any errant spans smaller than this
 /// window will be removed and any gaps larger than this window will be filled.
 /// 
 #[catch_panic(ptr::null_mut())]
@@ -50,6 +128,145 @@ pub extern "C" fn alass_voice_activity_clean(activity: *mut VoiceActivity, openi
     to_ptr(activity.clean(opening_radius, closing_radius))
 }
 
+///
+/// Cleans voice-activity data with durations given in milliseconds
+/// 
+/// Unlike `alass_voice_activity_clean()`, the result does not depend on the frame length
+/// (see `alass_vad_config_set_frame_millis()`). Steps are applied in the order listed and
+/// each is disabled when zero:
+/// 
+/// * `max_gap_ms`: Gaps between speech spans up to this long are bridged.
+/// * `min_speech_ms`: Speech spans shorter than this are then removed.
+/// * `onset_ms`: Speech spans are extended by this much before their start.
+/// * `offset_ms`: Speech spans are extended by this much past their end.
+/// 
+/// Durations are rounded to whole frames. Returns null if any enabled duration is shorter
+/// than a frame. The result is a clone of the original voice activity buffer.
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_voice_activity_clean_ms(activity: *mut VoiceActivity, max_gap_ms: u32, min_speech_ms: u32, onset_ms: u32, offset_ms: u32) -> *mut VoiceActivity {
+    if activity.is_null() {
+        error!("Invalid parameter: voice activity pointer is null");
+        return ptr::null_mut()
+    }
+
+    let activity = &*from_ptr(activity);
+    let cleanup = Cleanup {
+        max_gap_millis: max_gap_ms as u64,
+        min_speech_millis: min_speech_ms as u64,
+        onset_millis: onset_ms as u64,
+        offset_millis: offset_ms as u64
+    };
+    match activity.clean_ms(&cleanup) {
+        Ok(cleaned) => to_ptr(cleaned),
+        Err(e) => {
+            error!("Invalid parameter: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+///
+/// Excludes music-dominated regions from voice activity
+/// 
+/// Songs and scores are often detected as speech but rarely carry subtitles. Frames whose
+/// likelihood of music (between 0 and 1) reaches `threshold` are marked as non-speech. The
+/// result is a clone of the original voice activity buffer to be converted with
+/// `alass_timespans_compute()`. A threshold of 0.5 suits most films. No frames are excluded
+/// unless music discrimination was enabled (see `alass_vad_config_set_music_discrimination()`).
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_voice_activity_without_music(activity: *mut VoiceActivity, threshold: f32) -> *mut VoiceActivity {
+    if activity.is_null() {
+        error!("Invalid parameter: voice activity pointer is null");
+        return ptr::null_mut()
+    }
+
+    let activity = &*from_ptr(activity);
+    to_ptr(activity.without_music(threshold))
+}
+
+///
+/// Down-weights speech in music-dominated regions of voice activity
+/// 
+/// Speech likelihood of each frame is scaled by `1 - weight * music`, where `music` is the
+/// likelihood of music in that frame, and speech is detected anew from the result. Unlike
+/// `alass_voice_activity_without_music()` this keeps clear speech over faint music. `weight`
+/// lies between 0 (no effect) and 1. The result is a clone of the original voice activity
+/// buffer to be converted with `alass_timespans_compute()`. No frames are down-weighted unless
+/// music discrimination was enabled (see `alass_vad_config_set_music_discrimination()`).
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_voice_activity_weight_music(activity: *mut VoiceActivity, weight: f32) -> *mut VoiceActivity {
+    if activity.is_null() {
+        error!("Invalid parameter: voice activity pointer is null");
+        return ptr::null_mut()
+    }
+
+    let activity = &*from_ptr(activity);
+    to_ptr(activity.weight_music(weight))
+}
+
+///
+/// Saves voice activity to disk with the given `filename` (see `alass_voice_activity_load()`)
+/// 
+/// Along with speech, the frame length, detector settings and the sample rate and channel
+/// count of the source audio are saved, so that the voice activity can later be cleaned and
+/// converted with other settings without decoding the audio again.
+/// 
+#[catch_panic(ALASS_INTERNAL_ERROR)]
+#[no_mangle]
+pub extern "C" fn alass_voice_activity_save(activity: *mut VoiceActivity, filename: *const c_char) -> ResultCode {
+    if activity.is_null() {
+        error!("Invalid parameter: voice activity pointer is null");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let filename_str = from_cstring(filename);
+    if filename_str.is_none() {
+        error!("Invalid parameter: filename is invalid");
+        return ALASS_INVALID_PARAMS;
+    }
+
+    let activity = &*from_ptr(activity);
+    match activity.save(&filename_str.unwrap()) {
+        Ok(()) => ALASS_SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            match e {
+                VoiceActivitySaveError::SerializeError { .. } => ALASS_SERIALIZE_ERROR,
+                VoiceActivitySaveError::WriteError { .. } => ALASS_WRITE_ERROR
+            }
+        }
+    }
+}
+
+///
+/// Loads voice activity saved to disk (see `alass_voice_activity_save()`). Returns null if
+/// no file exists at the given path, or if it does not hold voice activity or was saved by
+/// a newer version of the library.
+/// 
+#[catch_panic(ptr::null_mut())]
+#[no_mangle]
+pub extern "C" fn alass_voice_activity_load(filename: *const c_char) -> *mut VoiceActivity {
+    let filename_str = from_cstring(filename);
+    if filename_str.is_none() {
+        error!("Invalid parameter: filename is invalid");
+        return ptr::null_mut();
+    }
+
+    match VoiceActivity::load(&filename_str.unwrap()) {
+        Ok(activity) => to_ptr(activity),
+        Err(e) => {
+            error!("{}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
 ///
 /// Deallocates voice activity buffer.
 /// 
@@ -1,147 +1,650 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
 
 use std::vec;
 use std::fs::File;
 use std::io;
 use std::io::{BufReader, prelude::*};
+use std::path::Path;
 use std::{error::Error, fmt};
 
-#[cfg(any(feature = "debug-sample-data", feature = "debug-voice-activity-data"))]
-use std::{io::Write, path::PathBuf};
-
-#[cfg(feature = "debug-sample-data")]
-use std::slice;
+use byteorder::{ByteOrder, LittleEndian};
 
 use log::error;
-use webrtc_vad::{Vad, SampleRate, VadMode};
-use byteorder::{ByteOrder, LittleEndian};
 
-use crate::VoiceActivity;
+use crate::{VoiceActivity, ActivitySource, VadConfig, VadRate, VoiceDetector, SPEECH_LIKELIHOOD};
+use crate::{AudioFormat, ChannelFusion, Downmix, SampleFormat, Sample};
+use crate::resample::Resampler;
+use crate::preprocess::Preprocessor;
+use crate::music::MusicClassifier;
+use crate::downmix::Downmixer;
+use crate::capture::Capture;
 
 use AudioSinkError::*;
 
-/// The sensitivity of the WebRTC voice-activity detector. Lowering the sensitivity
-/// greatly reduces false positives but increases the chance of missed detections.
-const VAD_MODE: VadMode = VadMode::LowBitrate;
-
-/// The sample rate expected by the voice-activity detector
-const VAD_SAMPLE_RATE: SampleRate = SampleRate::Rate8kHz;
-
-/// The millisecond duration of each chunk to be processed by the voice-activity
-/// detector. The WebRTC VAD expects chunks of 10, 20, or 30ms.
-const CHUNK_MILLIS: usize = 30;
-
-/// The number of samples per chunk to be processed by the voice-activity detector
-const CHUNK_SAMPLES: usize = CHUNK_MILLIS * VAD_SAMPLE_RATE as usize / 1000;
-
 ///
 /// Receives audio samples to be processed for voice-activity and used as reference
 /// by the synchronization process
 /// 
-pub struct AudioSink {
+/// Voice-activity is detected by `D`, dynamically dispatched to the detector chosen by
+/// `VadConfig::backend` unless a specific detector is given (see `with_detector`). Formats
+/// fusing voice-activity across channels run a detector for each (see `Downmix::Fused`).
+/// 
+pub struct AudioSink<D: VoiceDetector = Box<dyn VoiceDetector>> {
     pub state: AudioSinkState,
-    sample_buffer: Vec<i16>,
-    vad: Vad,
+    channels: usize,
+    sample_rate: u32,
+    sample_format: SampleFormat,
+    timeline: Option<Timeline>,
+    vad_config: VadConfig,
+    chunk_samples: usize,
+    convert_buffer: Vec<i16>,
+    downmixer: Option<Downmixer>,
+    downmix_buffer: Vec<i16>,
+    lanes: Vec<Lane<D>>,
+    lane_buffer: Vec<i16>,
+    fusion: ChannelFusion,
+    preprocess_buffer: Vec<i16>,
+    fusion_buffer: Vec<f32>,
+    music_buffer: Vec<i16>,
+    music_classifier: Option<MusicClassifier>,
     vad_buffer: Vec<bool>,
-
-    #[cfg(feature = "debug-sample-data")]
-    sample_file: File,
-
-    #[cfg(feature = "debug-voice-activity-data")]
-    vad_file: File,
+    likelihood_buffer: Vec<f32>,
+    known_buffer: Vec<bool>,
+    samples_received: u64,
+    chunks_processed: u64,
+    speech_chunks: u64,
+    capture: Option<Capture>
 }
 
 impl Default for AudioSink {
 
     ///
-    /// Creates a new `AudioSink` instance ready to accept sample data
+    /// Creates a new `AudioSink` instance ready to accept 8kHz sample data
     /// 
     fn default() -> Self {
+        let vad_config = VadConfig::default();
+        let detector = vad_config.backend.detector(&vad_config);
+        AudioSink::new(&AudioFormat::mono(vad_config.rate.hz()), None, vec![Lane::new(None, &vad_config, detector)], vad_config)
+    }
+}
+
+impl AudioSink {
+
+    ///
+    /// Creates a new `AudioSink` instance accepting audio in the given format
+    /// 
+    /// Multi-channel audio is mixed down to mono according to `format.downmix` (or split into
+    /// the channels to be fused) and resampled on the fly to `vad_rate`, the rate at which
+    /// voice-activity is detected. Higher VAD rates take more of the speech spectrum into
+    /// account at the expense of speed.
+    /// 
+    pub fn with_format(format: &AudioFormat, vad_rate: VadRate) -> Result<Self, AudioSinkError> {
+        AudioSink::with_config(format, VadConfig { rate: vad_rate, ..VadConfig::default() })
+    }
+
+    ///
+    /// Creates a new `AudioSink` instance accepting audio in the given format and detecting
+    /// voice-activity with the given settings (see `VadConfig`)
+    /// 
+    /// Audio is mixed down and resampled to `vad_config.rate` as with `with_format`.
+    /// 
+    pub fn with_config(format: &AudioFormat, vad_config: VadConfig) -> Result<Self, AudioSinkError> {
+        let lane_count = format.fused_channels().map(|c| c.len()).unwrap_or(1);
+        let detectors = (0 .. lane_count).map(|_| vad_config.backend.detector(&vad_config)).collect();
+        AudioSink::with_detectors(format, vad_config, detectors)
+    }
+
+    ///
+    /// Loads sample data captured to file (for debugging, see `start_capture`)
+    /// 
+    pub fn load_sample_data(filename: &str) -> Result<Vec<i16>, io::Error> {
+        let file = File::open(String::from(filename))?;
+        let bytes = &mut vec![];
+        BufReader::new(file).read_to_end(bytes)?;
+        let samples = bytes.as_slice().chunks_exact(2).map(LittleEndian::read_i16).collect();
+        Ok(samples)
+    }
+
+    ///
+    /// Loads voice-activity data captured to file (for debugging, see `start_capture`)
+    /// 
+    /// Only holds whether each processed chunk is speech. See `VoiceActivity::load` for the
+    /// voice-activity saved when the sink is closed.
+    /// 
+    pub fn load_vad_data(filename: &str) -> Result<Vec<bool>, io::Error> {
+        let file = File::open(String::from(filename))?;
+        let bytes = &mut vec![];
+        BufReader::new(file).read_to_end(bytes)?;
+        let vad_data = bytes.iter().map(|b| *b != 0).collect();
+        Ok(vad_data)
+    }
+}
+
+impl<D: VoiceDetector> AudioSink<D> {
+
+    ///
+    /// Creates a new `AudioSink` instance accepting audio in the given format and detecting
+    /// voice-activity with the given detector
+    /// 
+    /// The detector receives frames of `vad_config.frame` at `vad_config.rate`.
+    /// `vad_config.backend` is ignored. Formats fusing voice-activity across several channels
+    /// require a detector for each (see `with_detectors`).
+    /// 
+    pub fn with_detector(format: &AudioFormat, vad_config: VadConfig, detector: D) -> Result<Self, AudioSinkError> {
+        AudioSink::with_detectors(format, vad_config, vec![detector])
+    }
+
+    ///
+    /// Creates a new `AudioSink` instance accepting audio in a format fusing voice-activity
+    /// across channels (see `Downmix::Fused`), detecting voice-activity on each channel with
+    /// the corresponding detector
+    /// 
+    /// There must be exactly one detector per fused channel (see `AudioFormat::fused_channels`),
+    /// or a single detector for formats mixed down to mono.
+    /// 
+    pub fn with_detectors(format: &AudioFormat, vad_config: VadConfig, detectors: Vec<D>) -> Result<Self, AudioSinkError> {
+        format.validate()?;
+        let fused_channels = format.fused_channels();
+        let lane_count = fused_channels.as_ref().map(|c| c.len()).unwrap_or(1);
+        if detectors.len() != lane_count {
+            return Err(InvalidFormat { msg: format!("expected one detector per fused channel (detectors={}, channels={})", detectors.len(), lane_count) });
+        }
+        let downmixer = if format.channels > 1 {
+            Some(match fused_channels {
+                Some(channels) => Downmixer::with_mixes(channels.iter()
+                    .map(|c| (0 .. format.channels).map(|i| if i == *c { 1.0 } else { 0.0 }).collect())
+                    .collect()),
+                None => Downmixer::new(format.downmix_weights())
+            })
+        } else {
+            None
+        };
+        let vad_rate = vad_config.rate;
+        let lanes = detectors.into_iter()
+            .map(|detector| {
+                let resampler = if format.sample_rate != vad_rate.hz() {
+                    Some(Resampler::new(format.sample_rate, vad_rate.hz()))
+                } else {
+                    None
+                };
+                Lane::new(resampler, &vad_config, detector)
+            })
+            .collect();
+        Ok(AudioSink::new(format, downmixer, lanes, vad_config))
+    }
+
+    fn new(format: &AudioFormat, downmixer: Option<Downmixer>, lanes: Vec<Lane<D>>, vad_config: VadConfig) -> Self {
         AudioSink {
             state: AudioSinkState::Open,
-            sample_buffer: Vec::new(),
-            vad: Vad::new_with_rate_and_mode(VAD_SAMPLE_RATE, VAD_MODE),
+            channels: format.channels,
+            sample_rate: format.sample_rate,
+            sample_format: format.sample_format,
+            timeline: None,
+            vad_config,
+            chunk_samples: vad_config.frame_samples(),
+            convert_buffer: Vec::new(),
+            downmixer,
+            downmix_buffer: Vec::new(),
+            lanes,
+            lane_buffer: Vec::new(),
+            fusion: match format.downmix {
+                Downmix::Fused(fusion) => fusion,
+                _ => ChannelFusion::Any
+            },
+            preprocess_buffer: Vec::new(),
+            fusion_buffer: Vec::new(),
+            music_buffer: Vec::new(),
+            music_classifier: if vad_config.music_discrimination { Some(MusicClassifier::new(vad_config.frame.millis())) } else { None },
             vad_buffer: Vec::new(),
+            likelihood_buffer: Vec::new(),
+            known_buffer: Vec::new(),
+            samples_received: 0,
+            chunks_processed: 0,
+            speech_chunks: 0,
+            capture: None
+        }
+    }
 
-            #[cfg(feature = "debug-sample-data")]
-            sample_file: AudioSink::create_debug_file("alass-sample-data.raw"),
+    ///
+    /// Recieve incoming samples
+    /// 
+    /// Samples must be at the sample rate and channel count given when the `AudioSink` was
+    /// created (8kHz mono by default), with multi-channel frames interleaved. Voice-activity data
+    /// is processed on the fly in frames of `VadConfig::frame`. Remaining samples are buffered
+    /// until the next invocation or the `AudioSink` is closed.
+    /// 
+    pub fn send_samples(self: &mut Self, samples: &[i16]) -> Result<(), AudioSinkError> {
+        if self.state == AudioSinkState::Open {
+            self.samples_received += samples.len() as u64;
+            let timeline = self.timeline.get_or_insert_with(|| Timeline::new(0));
+            let skip = timeline.skip_samples.min(samples.len());
+            let samples = &samples[skip ..];
+            timeline.skip_samples -= skip;
+            timeline.segment_samples += samples.len() as u64;
+            match self.downmixer.as_mut() {
+                Some(downmixer) => {
+                    let mut mixed = std::mem::take(&mut self.downmix_buffer);
+                    downmixer.process(samples, &mut mixed);
+                    let result = self.send_lane_samples(&mixed);
+                    mixed.clear();
+                    self.downmix_buffer = mixed;
+                    result
+                },
+                None => self.send_lane_samples(samples)
+            }
+        } else {
+            Err(AudioSinkError::SinkClosed)
+        }
+    }
 
-            #[cfg(feature = "debug-voice-activity-data")]
-            vad_file: AudioSink::create_debug_file("alass-voice-activity-data")
+    ///
+    /// Recieve incoming samples presented at the given timestamp (millis)
+    /// 
+    /// Equivalent to `set_timestamp` followed by `send_samples`.
+    /// 
+    pub fn send_samples_at(self: &mut Self, pts_millis: i64, samples: &[i16]) -> Result<(), AudioSinkError> {
+        self.set_timestamp(pts_millis)?;
+        self.send_samples(samples)
+    }
+
+    ///
+    /// Declares the presentation timestamp (millis) of the next samples to be sent
+    /// 
+    /// The first timestamp determines the start of the resulting `VoiceActivity` (see
+    /// `VoiceActivity::start_millis`) so that audio starting at an offset lines up with the
+    /// video timeline. Streams without timestamps start at zero.
+    /// 
+    /// Later timestamps are compared against the duration of audio received so far. Gaps
+    /// longer than a VAD frame (e.g. after seeking or skipping corrupt packets) are filled
+    /// with non-speech without being processed, and samples overlapping audio already
+    /// received are dropped. Smaller deviations are ignored. Gaps are marked unknown so that
+    /// alignment ignores them, allowing only select windows of the audio to be decoded (see
+    /// `sample_windows`).
+    /// 
+    pub fn set_timestamp(self: &mut Self, pts_millis: i64) -> Result<(), AudioSinkError> {
+        if self.state != AudioSinkState::Open {
+            return Err(AudioSinkError::SinkClosed);
         }
 
+        let timeline = match self.timeline.as_mut() {
+            Some(timeline) => timeline,
+            None => {
+                self.timeline = Some(Timeline::new(pts_millis));
+                return Ok(())
+            }
+        };
+
+        let frames = timeline.segment_samples / self.channels as u64;
+        let expected_millis = timeline.segment_millis as f64 + frames as f64 * 1000.0 / self.sample_rate as f64;
+        let deviation_millis = pts_millis as f64 - expected_millis;
+        let chunk_millis = self.vad_config.frame.millis() as f64;
+        timeline.skip_samples = 0;
+
+        if deviation_millis > chunk_millis {
+            let target_chunk = ((pts_millis - timeline.start_millis) as f64 / chunk_millis).round() as usize;
+            timeline.segment_millis = pts_millis;
+            timeline.segment_samples = 0;
+            self.skip_to_chunk(target_chunk)?;
+        } else if deviation_millis < -chunk_millis {
+            let overlap_frames = (-deviation_millis * self.sample_rate as f64 / 1000.0).round() as usize;
+            timeline.skip_samples = overlap_frames * self.channels;
+        }
+        Ok(())
     }
-}
 
-impl AudioSink {
+    ///
+    /// Finishes processing audio received so far and fills voice-activity with non-speech
+    /// up to the given chunk, restarting the stream from there
+    /// 
+    fn skip_to_chunk(self: &mut Self, target_chunk: usize) -> Result<(), AudioSinkError> {
+        for lane in self.lanes.iter_mut() {
+            if let Some(resampler) = lane.resampler.as_mut() {
+                resampler.reset(&mut lane.sample_buffer);
+            }
+        }
+        self.process_chunks()?;
+        self.flush_chunk()?;
+        while self.vad_buffer.len() < target_chunk {
+            self.vad_buffer.push(false);
+            self.likelihood_buffer.push(0.0);
+            self.known_buffer.push(false);
+            if let Some(classifier) = self.music_classifier.as_mut() {
+                classifier.push_unknown();
+            }
+        }
+        Ok(())
+    }
 
     ///
-    /// Recieve incoming samples
+    /// Recieve incoming samples of any supported type (see `Sample`)
     /// 
-    /// Voice-activity data is processed on the fly in `CHUNK_SAMPLES` sized chunks. Remaining
-    /// samples are buffered until the next invocation or the `AudioSink` is closed.
+    /// Multi-channel frames must be interleaved. See `send_samples` for details.
     /// 
-    pub fn send_samples(self: &mut AudioSink, samples: &[i16]) -> Result<(), AudioSinkError> {
-        if self.state == AudioSinkState::Open {
-            if self.sample_buffer.len() + samples.len() >= CHUNK_SAMPLES {
+    pub fn send<S: Sample>(self: &mut Self, samples: &[S]) -> Result<(), AudioSinkError> {
+        let mut converted = std::mem::take(&mut self.convert_buffer);
+        converted.extend(samples.iter().map(|s| s.to_i16()));
+        let result = self.send_samples(&converted);
+        converted.clear();
+        self.convert_buffer = converted;
+        result
+    }
 
-                // Combine sink and incoming samples to produce first complete chunk (copy)
-                let mut first_chunk = Vec::with_capacity(CHUNK_SAMPLES);
-                first_chunk.extend_from_slice(self.sample_buffer.as_slice());
-                let len2 = CHUNK_SAMPLES - self.sample_buffer.len();
-                first_chunk.extend_from_slice(&samples[0..len2]);
-                self.process_chunk(&first_chunk)?;
+    ///
+    /// Recieve incoming planar samples of any supported type (see `Sample`)
+    /// 
+    /// Each plane holds the samples of a single channel and there must be exactly one
+    /// plane per channel, each of the same length.
+    /// 
+    pub fn send_planar<S: Sample>(self: &mut Self, planes: &[&[S]]) -> Result<(), AudioSinkError> {
+        if planes.len() != self.channels {
+            return Err(InvalidFormat { msg: format!("expected one plane per channel (planes={}, channels={})", planes.len(), self.channels) });
+        }
+        let frames = planes.first().map(|p| p.len()).unwrap_or(0);
+        if planes.iter().any(|p| p.len() != frames) {
+            return Err(InvalidFormat { msg: String::from("planes must be of equal length") });
+        }
+        let mut converted = std::mem::take(&mut self.convert_buffer);
+        converted.extend((0 .. frames).flat_map(|i| planes.iter().map(move |p| p[i].to_i16())));
+        let result = self.send_samples(&converted);
+        converted.clear();
+        self.convert_buffer = converted;
+        result
+    }
 
-                // Split the rest of the incoming samples into exactly sized chunks (no copy)
-                let remaining_chunks = samples[len2..].chunks_exact(CHUNK_SAMPLES);
+    ///
+    /// Recieve incoming samples as raw little-endian bytes encoded according to the
+    /// `sample_format` given when the `AudioSink` was created (16-bit by default)
+    /// 
+    /// The buffer need not be aligned but must hold a whole number of samples. Multi-channel
+    /// frames must be interleaved.
+    /// 
+    pub fn send_bytes(self: &mut Self, bytes: &[u8]) -> Result<(), AudioSinkError> {
+        let sample_format = self.sample_format;
+        if !bytes.chunks_exact(sample_format.bytes()).remainder().is_empty() {
+            return Err(InvalidFormat { msg: format!("buffer length must be a multiple of the sample size (length={}, sample_size={})", bytes.len(), sample_format.bytes()) });
+        }
+        let mut converted = std::mem::take(&mut self.convert_buffer);
+        converted.extend(bytes.chunks_exact(sample_format.bytes()).map(|b| sample_format.decode(b)));
+        let result = self.send_samples(&converted);
+        converted.clear();
+        self.convert_buffer = converted;
+        result
+    }
 
-                // Save the remainder of the incoming samples to sample_buffer for next call (copy)
-                self.sample_buffer.clear();
-                self.sample_buffer.extend_from_slice(remaining_chunks.remainder());
+    ///
+    /// Resamples the mono samples of each lane, interleaved if there are several, to the VAD
+    /// sample rate and processes complete chunks
+    /// 
+    fn send_lane_samples(self: &mut Self, samples: &[i16]) -> Result<(), AudioSinkError> {
+        let lane_count = self.lanes.len();
+        let mut lane_samples = std::mem::take(&mut self.lane_buffer);
+        for (index, lane) in self.lanes.iter_mut().enumerate() {
+            let samples = if lane_count > 1 {
+                lane_samples.clear();
+                lane_samples.extend(samples.iter().skip(index).step_by(lane_count));
+                lane_samples.as_slice()
+            } else {
+                samples
+            };
+            match lane.resampler.as_mut() {
+                Some(resampler) => resampler.process(samples, &mut lane.sample_buffer),
+                None => lane.sample_buffer.extend_from_slice(samples)
+            }
+        }
+        self.lane_buffer = lane_samples;
+        self.process_chunks()
+    }
 
-                // Process exactly sized chunks (no copy)
-                for chunk in remaining_chunks {
-                    self.process_chunk(chunk)?;
-                }
+    ///
+    /// Processes all complete chunks buffered at the VAD sample rate. Lanes receive the same
+    /// number of samples, so that chunks of all lanes complete together.
+    /// 
+    fn process_chunks(self: &mut Self) -> Result<(), AudioSinkError> {
+        let chunk_samples = self.chunk_samples;
+        let buffered = self.lanes.iter().map(|l| l.sample_buffer.len()).min().unwrap_or(0);
+        let chunks = buffered / chunk_samples;
+        let mut result = Ok(());
+        for chunk in 0 .. chunks {
+            result = self.process_chunk(chunk * chunk_samples);
+            if result.is_err() {
+                break
+            }
+        }
+        for lane in self.lanes.iter_mut() {
+            lane.sample_buffer.drain(.. chunks * chunk_samples);
+        }
+        result
+    }
 
+    ///
+    /// Processes a single chunk of samples of every lane for voice activity, starting at the
+    /// given offset into the sample buffers
+    /// 
+    fn process_chunk(self: &mut Self, offset: usize) -> Result<(), AudioSinkError> {
+        let range = offset .. offset + self.chunk_samples;
+
+        // Music is told apart from speech by features that preprocessing would distort
+        if let Some(classifier) = self.music_classifier.as_mut() {
+            if self.lanes.len() > 1 {
+                let lane_count = self.lanes.len() as i32;
+                let lanes = &self.lanes;
+                self.music_buffer.clear();
+                self.music_buffer.extend(range.clone().map(|i| (lanes.iter().map(|l| l.sample_buffer[i] as i32).sum::<i32>() / lane_count) as i16));
+                classifier.push(&self.music_buffer);
             } else {
-                // Not enough data for a complete chunk, append samples to sample_buffer for next call
-                self.sample_buffer.extend_from_slice(samples);
+                classifier.push(&self.lanes[0].sample_buffer[range.clone()]);
             }
-            Ok(())
-        } else {
-            Err(AudioSinkError::SinkClosed)
         }
+
+        // Capture samples of the first lane ahead of preprocessing, which can be replayed
+        // from the settings saved along with voice activity
+        if let Some(capture) = self.capture.as_mut() {
+            capture.write_samples(&self.lanes[0].sample_buffer[range.clone()]);
+        }
+
+        let mut preprocessed = std::mem::take(&mut self.preprocess_buffer);
+        let mut likelihoods = std::mem::take(&mut self.fusion_buffer);
+        likelihoods.clear();
+        for lane in self.lanes.iter_mut() {
+
+            // Filter, gate and normalize samples before detection
+            let chunk = &lane.sample_buffer[range.clone()];
+            let chunk = match lane.preprocessor.as_mut() {
+                Some(preprocessor) => {
+                    preprocessor.process(chunk, &mut preprocessed);
+                    preprocessed.as_slice()
+                },
+                None => chunk
+            };
+
+            // Detect voice activity
+            match lane.detector.likelihood(chunk) {
+                Ok(likelihood) => likelihoods.push(likelihood),
+                Err(e) => {
+                    self.preprocess_buffer = preprocessed;
+                    self.fusion_buffer = likelihoods;
+                    return Err(e)
+                }
+            }
+        }
+        let likelihood = self.fusion.fuse(&mut likelihoods);
+        let is_voice = likelihood >= SPEECH_LIKELIHOOD;
+
+        // Store voice activity for this chunk to buffer
+        self.vad_buffer.push(is_voice);
+        self.likelihood_buffer.push(likelihood);
+        self.known_buffer.push(true);
+        self.chunks_processed += 1;
+        if is_voice {
+            self.speech_chunks += 1;
+        }
+
+        if let Some(capture) = self.capture.as_mut() {
+            capture.write_vad(is_voice);
+        }
+
+        self.preprocess_buffer = preprocessed;
+        self.fusion_buffer = likelihoods;
+        Ok(())
+    }
+
+    ///
+    /// Number of channels per incoming frame
+    /// 
+    pub fn channels(self: &Self) -> usize {
+        self.channels
     }
 
     ///
-    /// Processes a single chunk of samples for voice activity
+    /// Voice-activity detector settings
     /// 
-    /// Chunk must be exactly `CHUNK_SAMPLES` in length.
+    pub fn vad_config(self: &Self) -> VadConfig {
+        self.vad_config
+    }
+
+    ///
+    /// Encoding expected of samples sent as raw bytes (see `send_bytes`)
+    /// 
+    pub fn sample_format(self: &Self) -> SampleFormat {
+        self.sample_format
+    }
+
+    ///
+    /// Whether the `AudioSink` still accepts samples
     /// 
-    fn process_chunk(self: &mut AudioSink, chunk: &[i16]) -> Result<(), AudioSinkError> {
-        if chunk.len() != CHUNK_SAMPLES {
-            error!("Error processing samples: chunk length must be exactly CHUNK_SAMPLES ({})", CHUNK_SAMPLES);
-            return Err(VoiceDetectionError)
+    pub fn state(self: &Self) -> AudioSinkState {
+        self.state
+    }
+
+    ///
+    /// Number of individual samples received so far, counting each channel of a frame
+    /// 
+    pub fn samples_received(self: &Self) -> u64 {
+        self.samples_received
+    }
+
+    ///
+    /// Number of chunks processed for voice-activity so far, excluding gaps in the stream
+    /// (see `set_timestamp`). Samples short of a complete chunk are not processed until more
+    /// samples arrive or the `AudioSink` is closed.
+    /// 
+    pub fn chunks_processed(self: &Self) -> u64 {
+        self.chunks_processed
+    }
+
+    ///
+    /// Duration of audio processed for voice-activity so far (millis), excluding gaps in the
+    /// stream (see `chunks_processed`)
+    /// 
+    pub fn duration_processed(self: &Self) -> u64 {
+        self.chunks_processed * self.vad_config.frame.millis()
+    }
+
+    ///
+    /// Fraction of chunks processed so far that were detected as speech, or zero if no
+    /// chunks have been processed
+    /// 
+    pub fn speech_ratio(self: &Self) -> f64 {
+        if self.chunks_processed > 0 {
+            self.speech_chunks as f64 / self.chunks_processed as f64
+        } else {
+            0.0
         }
+    }
 
-        // Detect voice activity
-        let vad_result = self.vad.is_voice_segment(chunk);
-        match vad_result {
-            Ok(is_voice) => {
-                // Store voice activity for this chunk to buffer
-                self.vad_buffer.push(is_voice);
+    ///
+    /// Starts capturing the audio and voice-activity processed from now on to files in the
+    /// given directory, returning the name of the capture session (for debugging)
+    /// 
+    /// Files are named after the session, so that neither several sinks nor several runs
+    /// overwrite each other's:
+    /// 
+    /// * `<session>-samples.raw`: Samples of each processed chunk ahead of preprocessing and
+    ///    detection, mono 16-bit little-endian at the VAD rate (see `load_sample_data`). Only
+    ///    the first channel is captured when fusing channels.
+    /// 
+    /// * `<session>-vad.raw`: Whether each processed chunk is speech, one byte per chunk
+    ///    (see `load_vad_data`).
+    /// 
+    /// * `<session>-activity.bin`: The resulting voice-activity along with the detector
+    ///    settings, saved when the sink is closed (see `VoiceActivity::load`).
+    /// 
+    /// Any capture in progress is stopped first. Resetting the sink starts a new session in
+    /// the same directory. Errors writing files are logged and stop the capture without
+    /// affecting voice-activity.
+    /// 
+    pub fn start_capture(self: &mut Self, dir: &str) -> Result<String, AudioSinkError> {
+        if self.state != AudioSinkState::Open {
+            return Err(SinkClosed);
+        }
+        self.stop_capture();
+        let capture = Capture::start(Path::new(dir))
+            .map_err(|cause| CaptureError { cause, path: dir.to_string() })?;
+        let session = capture.session().to_string();
+        self.capture = Some(capture);
+        Ok(session)
+    }
 
-                // Dump voice activity data to file for debugging
-                #[cfg(feature = "debug-voice-activity-data")]
-                AudioSink::dump_vad(&is_voice, &mut self.vad_file);
-            },
-            Err(_) => return Err(VoiceDetectionError)
+    ///
+    /// Stops capturing audio and voice-activity, flushing files (see `start_capture`).
+    /// Voice-activity is only saved if the sink was closed while capturing.
+    /// 
+    pub fn stop_capture(self: &mut Self) {
+        if let Some(capture) = self.capture.take() {
+            capture.finish(None);
         }
+    }
 
-        // Dump samples to file for debugging
-        #[cfg(feature = "debug-sample-data")]
-        AudioSink::dump_samples(chunk, &mut self.sample_file);
+    ///
+    /// Name of the capture session in progress, if any (see `start_capture`)
+    /// 
+    pub fn capture_session(self: &Self) -> Option<&str> {
+        self.capture.as_ref().map(|c| c.session())
+    }
 
+    ///
+    /// Returns the `AudioSink` to its initial state, discarding all samples and
+    /// voice-activity data received so far, so that it can be reused for another stream
+    /// with the same format and settings
+    /// 
+    pub fn reset(self: &mut Self) {
+        if let Some(capture) = self.capture.take() {
+            let dir = capture.dir().to_path_buf();
+            capture.finish(None);
+            self.capture = Capture::start(&dir)
+                .map_err(|e| error!("Error restarting capture after reset (msg='{}', dir='{}')", e, dir.display()))
+                .ok();
+        }
+        self.state = AudioSinkState::Open;
+        self.timeline = None;
+        if let Some(downmixer) = self.downmixer.as_mut() {
+            downmixer.reset();
+        }
+        for lane in self.lanes.iter_mut() {
+            lane.reset();
+        }
+        if let Some(classifier) = self.music_classifier.as_mut() {
+            classifier.reset();
+        }
+        self.vad_buffer.clear();
+        self.likelihood_buffer.clear();
+        self.known_buffer.clear();
+        self.samples_received = 0;
+        self.chunks_processed = 0;
+        self.speech_chunks = 0;
+    }
+
+    ///
+    /// Processes buffered samples short of a complete chunk, padded with silence
+    /// 
+    fn flush_chunk(self: &mut Self) -> Result<(), AudioSinkError> {
+        if self.lanes.iter().any(|l| !l.sample_buffer.is_empty()) {
+            let chunk_samples = self.chunk_samples;
+            for lane in self.lanes.iter_mut() {
+                lane.sample_buffer.resize(chunk_samples, 0);
+            }
+            self.process_chunks()?;
+        }
         Ok(())
     }
 
@@ -151,15 +654,19 @@ impl AudioSink {
     /// This flushes any remaining samples and finishes processing voice-activity.
     /// `AudioSink` will no longer accept samples once closed.
     /// 
-    pub fn close(self: &mut AudioSink) -> Result<(), AudioSinkError> {
+    pub fn close(self: &mut Self) -> Result<(), AudioSinkError> {
         if self.state == AudioSinkState::Open {
-            let buf_len = self.sample_buffer.len();
-            if buf_len > 0 {
-                let chunk = &mut vec![0i16; CHUNK_SAMPLES];
-                chunk[..buf_len].clone_from_slice(self.sample_buffer.as_slice());
-                self.process_chunk(chunk.as_slice())?;
+            for lane in self.lanes.iter_mut() {
+                if let Some(resampler) = lane.resampler.as_mut() {
+                    resampler.flush(&mut lane.sample_buffer);
+                }
+            }
+            self.process_chunks()?;
+            self.flush_chunk()?;
+            self.state = AudioSinkState::Closed;
+            if let Some(capture) = self.capture.take() {
+                capture.finish(Some(&self.closed_voice_activity()));
             }
-            self.state = AudioSinkState::Closed
         }
         Ok(())
     }
@@ -167,69 +674,106 @@ impl AudioSink {
     ///
     /// Returns voice-activity data, closing the `AudioSink` if it has not been already
     /// 
+    /// Errors while closing are logged and the voice-activity processed so far is returned.
+    /// Use `close` followed by `try_voice_activity` to handle them instead.
+    /// 
     pub fn voice_activity(self: &mut Self) -> VoiceActivity {
-        let _ = self.close();
-        VoiceActivity { data: self.vad_buffer.clone(), chunk_millis: CHUNK_MILLIS as u64 }
+        if let Err(e) = self.close() {
+            error!("Error closing audio sink, voice activity may be incomplete: {}", e);
+        }
+        self.closed_voice_activity()
     }
 
-    #[cfg(any(feature = "debug-sample-data", feature = "debug-voice-activity-data"))]
-    fn create_debug_file(filename: &str) -> File {
-        let mut path = match std::env::var_os("LIBALASS_DEBUG_DATA_DIR") {
-            Some(dir) => PathBuf::from(dir),
-            None => match std::env::current_dir() {
-                Ok(dir) => dir,
-                Err(e) => panic!("[alass-util] Unable to obtain current working directory in order to dump raw sample data! (msg='{}')", e)
-            }
-        };
-        path.push(String::from(filename));
-        File::create(path).unwrap()
+    ///
+    /// Returns voice-activity data without closing the `AudioSink`, failing with `SinkOpen`
+    /// if it has not been closed yet
+    /// 
+    pub fn try_voice_activity(self: &Self) -> Result<VoiceActivity, AudioSinkError> {
+        match self.state {
+            AudioSinkState::Closed => Ok(self.closed_voice_activity()),
+            AudioSinkState::Open => Err(SinkOpen)
+        }
     }
 
-    #[cfg(feature = "debug-sample-data")]
-    fn dump_samples(chunk: &[i16], file: &mut File) {
-        let bytes: &[u8] = unsafe { slice::from_raw_parts(chunk.as_ptr() as *const u8, chunk.len()*2) };
-        match file.write_all(bytes) {
-            Ok(_) => (),
-            Err(e) => panic!("[libalass] Error writing raw sample data to file! (msg='{}')", e)
+    fn closed_voice_activity(self: &Self) -> VoiceActivity {
+        VoiceActivity {
+            data: self.vad_buffer.clone(),
+            likelihood: self.likelihood_buffer.clone(),
+            known: self.known_buffer.clone(),
+            music: self.music_classifier.as_ref().map(|c| c.likelihood()).unwrap_or_default(),
+            start_millis: self.timeline.as_ref().map(|t| t.start_millis).unwrap_or(0),
+            chunk_millis: self.vad_config.frame.millis(),
+            vad_config: self.vad_config,
+            source: Some(ActivitySource { name: None, sample_rate: self.sample_rate, channels: self.channels })
         }
     }
 
-    #[cfg(feature = "debug-voice-activity-data")]
-    fn dump_vad(is_voice: &bool, file: &mut File) {
-        let bytes: &[u8] = &[ if *is_voice { 1_u8 } else { 0u8 } ];
-        match file.write_all(bytes) {
-            Ok(_) => (),
-            Err(e) => panic!("[libalass] Error writing voice activity data to file! (msg='{}')", e)
+}
+
+///
+/// Processing state of a single mono stream analyzed for voice-activity, either the
+/// downmixed audio or one of the channels to be fused
+/// 
+/// * `sample_buffer`: Samples at the VAD sample rate short of a complete chunk.
+/// 
+struct Lane<D: VoiceDetector> {
+    resampler: Option<Resampler>,
+    sample_buffer: Vec<i16>,
+    preprocessor: Option<Preprocessor>,
+    detector: D
+}
+
+impl<D: VoiceDetector> Lane<D> {
+    fn new(resampler: Option<Resampler>, vad_config: &VadConfig, detector: D) -> Self {
+        Lane {
+            resampler,
+            sample_buffer: Vec::new(),
+            preprocessor: if vad_config.preprocessing.is_enabled() {
+                Some(Preprocessor::new(vad_config.preprocessing, vad_config.rate.hz(), vad_config.frame.millis()))
+            } else {
+                None
+            },
+            detector
         }
     }
 
-    ///
-    /// Loads sample data from file (for debugging)
-    /// 
-    pub fn load_sample_data(filename: &str) -> Result<Vec<i16>, io::Error> {
-        let file = File::open(String::from(filename))?;
-        let bytes = &mut vec![];
-        BufReader::new(file).read_to_end(bytes)?;
-        let samples = bytes.as_slice().chunks_exact(2).map(LittleEndian::read_i16).collect();
-        Ok(samples)
+    fn reset(self: &mut Self) {
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.reset(&mut Vec::new());
+        }
+        self.sample_buffer.clear();
+        if let Some(preprocessor) = self.preprocessor.as_mut() {
+            preprocessor.reset();
+        }
+        self.detector.reset();
     }
+}
 
-    ///
-    /// Loads voice-activity data from file (for debugging)
-    /// 
-    pub fn load_vad_data(filename: &str) -> Result<Vec<bool>, io::Error> {
-        let file = File::open(String::from(filename))?;
-        let bytes = &mut vec![];
-        BufReader::new(file).read_to_end(bytes)?;
-        let vad_data = bytes.iter().map(|b| *b != 0).collect();
-        Ok(vad_data)
+///
+/// Tracks the presentation time of incoming samples
+/// 
+/// * `start_millis`: Timestamp of the first sample received.
+/// * `segment_millis`: Timestamp at the start of the current continuous segment.
+/// * `segment_samples`: Samples received since the start of the current segment.
+/// * `skip_samples`: Samples still to be dropped as overlapping audio already received.
+/// 
+struct Timeline {
+    start_millis: i64,
+    segment_millis: i64,
+    segment_samples: u64,
+    skip_samples: usize
+}
+
+impl Timeline {
+    fn new(start_millis: i64) -> Self {
+        Timeline { start_millis, segment_millis: start_millis, segment_samples: 0, skip_samples: 0 }
     }
 }
 
 ///
 /// Represents current state of audio `AudioSink`
 /// 
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AudioSinkState { Open, Closed }
 
 ///
@@ -239,7 +783,10 @@ pub enum AudioSinkState { Open, Closed }
 pub enum AudioSinkError {
     SinkClosed,
     SinkOpen,
-    VoiceDetectionError
+    UnsupportedSampleRate { rate: u32 },
+    InvalidFormat { msg: String },
+    VoiceDetectionError,
+    CaptureError { cause: io::Error, path: String }
 }
 
 impl Error for AudioSinkError {}
@@ -249,7 +796,10 @@ impl fmt::Display for AudioSinkError {
         match self {
             AudioSinkError::SinkClosed => write!(f, "Cannot write samples to sink after it's been closed"),
             AudioSinkError::SinkOpen => write!(f, "Cannot cannot access voice-activity data until sink has been closed"),
-            AudioSinkError::VoiceDetectionError => write!(f, "An error occurred during voice-detection")
+            AudioSinkError::UnsupportedSampleRate { rate } => write!(f, "Unsupported sample rate (rate={}Hz)", rate),
+            AudioSinkError::InvalidFormat { msg } => write!(f, "Invalid audio format ({})", msg),
+            AudioSinkError::VoiceDetectionError => write!(f, "An error occurred during voice-detection"),
+            AudioSinkError::CaptureError { cause, path } => write!(f, "Unable to capture audio for debugging (msg='{}', path='{}')", cause, path)
         }
     }
 }
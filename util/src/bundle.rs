@@ -0,0 +1,711 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::fmt;
+use std::fs::{self, File};
+use std::path::Path;
+use std::io;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter, Cursor};
+use std::io::ErrorKind::{NotFound, InvalidData};
+use std::error::Error;
+use std::convert::{TryFrom, TryInto};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use encoding_rs::UTF_8;
+
+use log::{error, info, warn};
+
+use subparse::get_subtitle_format;
+
+use crate::{SyncOptions, SyncReport, SyncError, TimeSpans, VoiceActivity, sync_spans};
+use crate::util::detect_encoding_or;
+
+use BundleError::*;
+
+/// Leading bytes identifying a sync bundle
+const MAGIC: &[u8; 4] = b"ASYB";
+
+/// Version of the layout of sync bundles, increased whenever it changes. Newer versions are
+/// refused when loading.
+const VERSION: u16 = 1;
+
+/// Version of this library, recorded in bundles
+const LIBRARY_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Most differing subtitle lines listed when replaying a bundle
+const MAX_LINE_DIFFERENCES: usize = 20;
+
+/// Number of bundles replayed by this process, telling apart concurrent replays' files
+static REPLAY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+///
+/// Everything needed to reproduce a sync, recorded when `SyncOptions::record_bundle` is set
+/// and re-run with `replay_bundle`
+/// 
+/// * `library_version`: Version of `alass-util` which recorded the bundle.
+/// 
+/// * `sub_filename`: File name of the input subtitle, whose extension helps determine its
+///    format.
+/// 
+/// * `sub_data`: Contents of the input subtitle file.
+/// 
+/// * `sub_encoding`: Encoding requested for the input subtitle, if any.
+/// 
+/// * `detected_encoding`: Encoding detected from `sub_data`, used unless another is
+///    requested.
+/// 
+/// * `sub_format`: Name of the subtitle format detected from `sub_data`, if supported.
+/// 
+/// * `reference`: Reference the subtitle was synced to.
+/// 
+/// * `ref_fps`: Framerate of the reference video.
+/// 
+/// * `options`: Options of the sync. `record_bundle` is not recorded.
+/// 
+/// * `outcome`: Synced subtitle data, or the error the sync failed with.
+/// 
+pub struct SyncBundle {
+    pub library_version: String,
+    pub sub_filename: String,
+    pub sub_data: Vec<u8>,
+    pub sub_encoding: Option<String>,
+    pub detected_encoding: String,
+    pub sub_format: Option<String>,
+    pub reference: BundleReference,
+    pub ref_fps: f64,
+    pub options: SyncOptions,
+    pub outcome: SyncOutcome
+}
+
+///
+/// Reference recorded in a `SyncBundle`, either timespans given to `sync` or voice-activity
+/// given to `sync_with_activity`
+/// 
+pub enum BundleReference {
+    Spans(TimeSpans),
+    Activity(VoiceActivity)
+}
+
+impl BundleReference {
+
+    ///
+    /// Timespans the subtitle is aligned with
+    /// 
+    pub fn spans(self: &Self) -> TimeSpans {
+        match self {
+            BundleReference::Spans(spans) => spans.clone(),
+            BundleReference::Activity(activity) => TimeSpans::from(activity)
+        }
+    }
+}
+
+///
+/// Result of a recorded or replayed sync
+/// 
+/// * `Synced`: The synced subtitle data along with the alignment confidence.
+/// 
+/// * `Failed`: The kind of `SyncError` the sync failed with (e.g. `LowConfidence`) and its
+///    message.
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncOutcome {
+    Synced { output: Vec<u8>, confidence: f64 },
+    Failed { kind: String, msg: String }
+}
+
+impl fmt::Display for SyncOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyncOutcome::Synced { output, confidence } => write!(f, "synced (confidence={:.3}, bytes={})", confidence, output.len()),
+            SyncOutcome::Failed { kind, msg } => write!(f, "failed (kind={}, msg='{}')", kind, msg)
+        }
+    }
+}
+
+///
+/// Outcome of replaying a `SyncBundle` compared with the recorded outcome
+/// 
+/// * `recorded_version`: Version of `alass-util` which recorded the bundle.
+/// 
+/// * `differences`: Human-readable differences between the outcomes, such as differing
+///    confidence or subtitle lines. Empty if the replay reproduced the recorded outcome.
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundleReplay {
+    pub recorded_version: String,
+    pub recorded: SyncOutcome,
+    pub replayed: SyncOutcome,
+    pub differences: Vec<String>
+}
+
+impl BundleReplay {
+
+    ///
+    /// Whether the replay reproduced the recorded outcome
+    /// 
+    pub fn is_identical(self: &Self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for BundleReplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "BundleReplay(recorded_version={}, replayed_version={}, recorded={}, replayed={}, differences={})",
+            self.recorded_version, LIBRARY_VERSION, self.recorded, self.replayed, self.differences.len())?;
+        for difference in self.differences.iter() {
+            write!(f, "\n  {}", difference)?;
+        }
+        Ok(())
+    }
+}
+
+impl SyncBundle {
+
+    ///
+    /// Saves the bundle to disk (see `load`)
+    /// 
+    pub fn save(self: &Self, filename: &str) -> Result<(), BundleError> {
+        let file = File::create(Path::new(filename))
+            .map_err(|cause| WriteError { cause, path: filename.to_string() })?;
+        let bytes: Vec<u8> = self.try_into()
+            .map_err(|cause| SerializeError { cause })?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(bytes.as_slice())
+            .and_then(|_| writer.flush())
+            .map_err(|cause| WriteError { cause, path: filename.to_string() })?;
+        Ok(())
+    }
+
+    ///
+    /// Loads a bundle saved to disk (see `save`)
+    /// 
+    /// Fails with `DeserializeError` if the file does not hold a bundle or was saved by a
+    /// newer version of the library.
+    /// 
+    pub fn load(filename: &str) -> Result<Self, BundleError> {
+        let file = File::open(Path::new(filename))
+            .map_err(|cause| match cause.kind() {
+                NotFound => FileNotFound { path: filename.to_string() },
+                _ => ReadError { cause, path: filename.to_string() }
+            })?;
+        let bytes = &mut vec![];
+        BufReader::new(file)
+            .read_to_end(bytes)
+            .map_err(|cause| ReadError { cause, path: filename.to_string() })?;
+        let bundle = Self::try_from(bytes.as_slice())
+            .map_err(|cause| DeserializeError { cause })?;
+        Ok(bundle)
+    }
+
+    ///
+    /// Re-runs the recorded sync and compares its outcome with the recorded one (see
+    /// `replay_bundle`)
+    /// 
+    pub fn replay(self: &Self) -> Result<BundleReplay, BundleError> {
+        if self.library_version != LIBRARY_VERSION {
+            warn!("Replaying bundle recorded by another version (recorded_version={}, version={})", self.library_version, LIBRARY_VERSION);
+        }
+
+        // Subtitles are synced from and to files named like the original, whose extension
+        // may determine the format
+        let sub_filename = Path::new(&self.sub_filename).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let prefix = format!("alass-replay-{}-{}", std::process::id(), REPLAY_COUNT.fetch_add(1, Ordering::Relaxed));
+        let sub_path_in = std::env::temp_dir().join(format!("{}-in-{}", prefix, sub_filename));
+        let sub_path_out = std::env::temp_dir().join(format!("{}-out-{}", prefix, sub_filename));
+        let sub_path_in_str = sub_path_in.to_string_lossy().to_string();
+        let sub_path_out_str = sub_path_out.to_string_lossy().to_string();
+        fs::write(&sub_path_in, &self.sub_data)
+            .map_err(|cause| WriteError { cause, path: sub_path_in_str.clone() })?;
+
+        let options = SyncOptions { record_bundle: None, ..self.options.clone() };
+        let result = sync_spans(&sub_path_in_str, &sub_path_out_str, &self.reference.spans(), self.ref_fps, self.sub_encoding.clone(), &options);
+        let replayed = outcome(&result, &sub_path_out_str);
+        let _ = fs::remove_file(&sub_path_in);
+        let _ = fs::remove_file(&sub_path_out);
+
+        let differences = differences(&self.outcome, &replayed);
+        Ok(BundleReplay { recorded_version: self.library_version.clone(), recorded: self.outcome.clone(), replayed, differences })
+    }
+}
+
+///
+/// Re-runs a sync recorded to disk (see `SyncOptions::record_bundle`) with the recorded
+/// subtitle, reference and options, and compares its outcome with the recorded one
+/// 
+/// Syncs limited by a time budget may degrade differently on another machine, and so may
+/// not be reproduced exactly (see `SyncOptions::time_budget`).
+/// 
+pub fn replay_bundle(bundle_path: &str) -> Result<BundleReplay, BundleError> {
+    SyncBundle::load(bundle_path)?.replay()
+}
+
+///
+/// Syncs as `sync_spans` does and records a bundle reproducing the sync to `bundle_path`.
+/// Failing to record is logged without affecting the sync.
+/// 
+pub(crate) fn record_sync(
+    bundle_path: &str,
+    sub_path_in: &str,
+    sub_path_out: &str,
+    reference: BundleReference,
+    ref_fps: f64,
+    sub_encoding: Option<String>,
+    opt: &SyncOptions
+) -> Result<SyncReport, SyncError> {
+
+    // Read the input ahead of syncing, as it may be overwritten by the output
+    let sub_data = fs::read(sub_path_in);
+    let result = sync_spans(sub_path_in, sub_path_out, &reference.spans(), ref_fps, sub_encoding.clone(), opt);
+    let sub_data = match sub_data {
+        Ok(data) => data,
+        Err(e) => {
+            error!("Unable to record sync bundle, input subtitle is unreadable (msg='{}', path='{}')", e, sub_path_in);
+            return result;
+        }
+    };
+
+    let sub_path = Path::new(sub_path_in);
+    let bundle = SyncBundle {
+        library_version: LIBRARY_VERSION.to_string(),
+        sub_filename: sub_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+        sub_encoding,
+        detected_encoding: detect_encoding_or(&sub_data, UTF_8).name().to_string(),
+        sub_format: get_subtitle_format(sub_path.extension(), &sub_data).map(|f| f.get_name().to_string()),
+        sub_data,
+        reference,
+        ref_fps,
+        options: SyncOptions { record_bundle: None, ..opt.clone() },
+        outcome: outcome(&result, sub_path_out)
+    };
+    match bundle.save(bundle_path) {
+        Ok(()) => info!("Recorded sync bundle (path='{}')", bundle_path),
+        Err(e) => error!("Unable to record sync bundle ({})", e)
+    }
+    result
+}
+
+///
+/// Outcome of a sync whose output, if any, was written to `sub_path_out`
+/// 
+fn outcome(result: &Result<SyncReport, SyncError>, sub_path_out: &str) -> SyncOutcome {
+    match result {
+        Ok(report) => match fs::read(sub_path_out) {
+            Ok(output) => SyncOutcome::Synced { output, confidence: report.confidence },
+            Err(e) => SyncOutcome::Failed { kind: String::from("ReadError"), msg: format!("Unable to read synced subtitle (msg='{}')", e) }
+        },
+        Err(e) => SyncOutcome::Failed { kind: error_kind(e).to_string(), msg: e.to_string() }
+    }
+}
+
+///
+/// Name of the variant of a `SyncError`, which unlike its message does not mention paths
+/// 
+fn error_kind(e: &SyncError) -> &'static str {
+    match e {
+        SyncError::UnsupportedFormat { .. } => "UnsupportedFormat",
+        SyncError::ReadError { .. } => "ReadError",
+        SyncError::DoesNotExist { .. } => "DoesNotExist",
+        SyncError::PermissionDenied { .. } => "PermissionDenied",
+        SyncError::ParseError { .. } => "ParseError",
+        SyncError::WriteError { .. } => "WriteError",
+        SyncError::SerializeError { .. } => "SerializeError",
+        SyncError::LowConfidence { .. } => "LowConfidence",
+        SyncError::UnusableReference { .. } => "UnusableReference",
+        SyncError::InternalError { .. } => "InternalError"
+    }
+}
+
+///
+/// Differences between a recorded and replayed outcome, listing differing subtitle lines
+/// 
+fn differences(recorded: &SyncOutcome, replayed: &SyncOutcome) -> Vec<String> {
+    match (recorded, replayed) {
+        (SyncOutcome::Synced { output: recorded_output, confidence: recorded_confidence },
+         SyncOutcome::Synced { output: replayed_output, confidence: replayed_confidence }) => {
+            let mut differences = Vec::new();
+            if (recorded_confidence - replayed_confidence).abs() > 1e-9 {
+                differences.push(format!("confidence differs (recorded={:.6}, replayed={:.6})", recorded_confidence, replayed_confidence));
+            }
+            let recorded_text = String::from_utf8_lossy(recorded_output);
+            let replayed_text = String::from_utf8_lossy(replayed_output);
+            let recorded_lines: Vec<&str> = recorded_text.lines().collect();
+            let replayed_lines: Vec<&str> = replayed_text.lines().collect();
+            if recorded_lines.len() != replayed_lines.len() {
+                differences.push(format!("line count differs (recorded={}, replayed={})", recorded_lines.len(), replayed_lines.len()));
+            }
+            let differing_lines: Vec<String> = recorded_lines.iter().zip(replayed_lines.iter())
+                .enumerate()
+                .filter(|(_, (a, b))| a != b)
+                .map(|(i, (a, b))| format!("line {} differs (recorded='{}', replayed='{}')", i + 1, a, b))
+                .collect();
+            let omitted = differing_lines.len().saturating_sub(MAX_LINE_DIFFERENCES);
+            differences.extend(differing_lines.into_iter().take(MAX_LINE_DIFFERENCES));
+            if omitted > 0 {
+                differences.push(format!("{} more lines differ", omitted));
+            }
+            if differences.is_empty() && recorded_output != replayed_output {
+                differences.push(String::from("output differs in line endings or invalid characters"));
+            }
+            differences
+        },
+        (SyncOutcome::Failed { kind: recorded_kind, .. }, SyncOutcome::Failed { kind: replayed_kind, .. }) if recorded_kind == replayed_kind => Vec::new(),
+        (recorded, replayed) => vec![format!("outcome differs (recorded={}, replayed={})", recorded, replayed)]
+    }
+}
+
+///
+/// Serializes `SyncBundle` instance to raw bytes
+/// 
+/// Following the magic bytes and version come the fields in order of declaration. Strings
+/// and byte buffers are preceded by their length, optional values by a flag, and the
+/// reference by its kind followed by its own serialization (see `TimeSpans` and
+/// `VoiceActivity`).
+/// 
+impl<'a> TryInto<Vec<u8>> for &'a SyncBundle {
+    type Error = io::Error;
+
+    fn try_into(self: &'a SyncBundle) -> Result<Vec<u8>, Self::Error> {
+        fn write_data(bytes: &mut Vec<u8>, data: &[u8]) -> Result<(), io::Error> {
+            bytes.write_u64::<LittleEndian>(data.len() as u64)?;
+            bytes.extend(data);
+            Ok(())
+        }
+        fn write_str(bytes: &mut Vec<u8>, s: Option<&str>) -> Result<(), io::Error> {
+            bytes.write_u8(s.is_some() as u8)?;
+            write_data(bytes, s.unwrap_or("").as_bytes())
+        }
+        fn write_f64(bytes: &mut Vec<u8>, value: Option<f64>) -> Result<(), io::Error> {
+            bytes.write_u8(value.is_some() as u8)?;
+            bytes.write_f64::<LittleEndian>(value.unwrap_or(0.0))
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.write_u16::<LittleEndian>(VERSION)?;
+        write_str(&mut bytes, Some(&self.library_version))?;
+        write_str(&mut bytes, Some(&self.sub_filename))?;
+        write_data(&mut bytes, &self.sub_data)?;
+        write_str(&mut bytes, self.sub_encoding.as_deref())?;
+        write_str(&mut bytes, Some(&self.detected_encoding))?;
+        write_str(&mut bytes, self.sub_format.as_deref())?;
+
+        let reference: Vec<u8> = match &self.reference {
+            BundleReference::Spans(spans) => { bytes.write_u8(0)?; spans.try_into()? },
+            BundleReference::Activity(activity) => { bytes.write_u8(1)?; activity.try_into()? }
+        };
+        write_data(&mut bytes, &reference)?;
+        bytes.write_f64::<LittleEndian>(self.ref_fps)?;
+
+        // Destructured exhaustively, so that options added later cannot be left out of bundles
+        let SyncOptions {
+            interval, split_mode, split_penalty, split_penalty_auto, speed_optimization,
+            framerate_correction, time_budget, min_confidence, reject_unusable_reference, record_bundle: _
+        } = &self.options;
+        bytes.write_i64::<LittleEndian>(*interval)?;
+        bytes.write_u8(*split_mode as u8)?;
+        bytes.write_f64::<LittleEndian>(*split_penalty)?;
+        bytes.write_u8(*split_penalty_auto as u8)?;
+        write_f64(&mut bytes, *speed_optimization)?;
+        bytes.write_u8(*framerate_correction as u8)?;
+        bytes.write_u8(time_budget.is_some() as u8)?;
+        bytes.write_u64::<LittleEndian>(time_budget.map(|b| b.as_millis() as u64).unwrap_or(0))?;
+        write_f64(&mut bytes, *min_confidence)?;
+        bytes.write_u8(*reject_unusable_reference as u8)?;
+
+        match &self.outcome {
+            SyncOutcome::Synced { output, confidence } => {
+                bytes.write_u8(0)?;
+                bytes.write_f64::<LittleEndian>(*confidence)?;
+                write_data(&mut bytes, output)?;
+            },
+            SyncOutcome::Failed { kind, msg } => {
+                bytes.write_u8(1)?;
+                write_str(&mut bytes, Some(kind))?;
+                write_str(&mut bytes, Some(msg))?;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+///
+/// Deserializes `SyncBundle` instance from raw bytes, failing with `InvalidData` if they do
+/// not hold a bundle or come from a newer version
+/// 
+impl TryFrom<&[u8]> for SyncBundle {
+    type Error = io::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        fn invalid(msg: String) -> io::Error {
+            io::Error::new(InvalidData, msg)
+        }
+        fn read_data<'a>(rdr: &mut Cursor<&'a [u8]>) -> Result<&'a [u8], io::Error> {
+            let len = rdr.read_u64::<LittleEndian>()?;
+            let start = rdr.position();
+            let data: &'a [u8] = rdr.get_ref();
+            match start.checked_add(len) {
+                Some(end) if end <= data.len() as u64 => {
+                    rdr.set_position(end);
+                    Ok(&data[start as usize .. end as usize])
+                },
+                _ => Err(invalid(format!("truncated bundle (len={}, remaining_bytes={})", len, (data.len() as u64).saturating_sub(start))))
+            }
+        }
+        fn read_str(rdr: &mut Cursor<&[u8]>) -> Result<Option<String>, io::Error> {
+            let is_some = rdr.read_u8()? != 0;
+            let s = String::from_utf8(read_data(rdr)?.to_vec())
+                .map_err(|e| invalid(format!("invalid string in bundle (msg='{}')", e)))?;
+            Ok(if is_some { Some(s) } else { None })
+        }
+        fn read_f64(rdr: &mut Cursor<&[u8]>) -> Result<Option<f64>, io::Error> {
+            let is_some = rdr.read_u8()? != 0;
+            let value = rdr.read_f64::<LittleEndian>()?;
+            Ok(if is_some { Some(value) } else { None })
+        }
+
+        let mut rdr = Cursor::new(bytes);
+        let mut magic = [0u8; 4];
+        rdr.read_exact(&mut magic).map_err(|_| invalid("not a sync bundle".to_string()))?;
+        if &magic != MAGIC {
+            return Err(invalid("not a sync bundle".to_string()));
+        }
+        let version = rdr.read_u16::<LittleEndian>()?;
+        if version > VERSION {
+            return Err(invalid(format!("unsupported bundle version (version={}, supported={})", version, VERSION)));
+        }
+
+        let library_version = read_str(&mut rdr)?.unwrap_or_default();
+        let sub_filename = read_str(&mut rdr)?.unwrap_or_default();
+        let sub_data = read_data(&mut rdr)?.to_vec();
+        let sub_encoding = read_str(&mut rdr)?;
+        let detected_encoding = read_str(&mut rdr)?.unwrap_or_default();
+        let sub_format = read_str(&mut rdr)?;
+
+        let reference = match rdr.read_u8()? {
+            0 => BundleReference::Spans(TimeSpans::try_from(read_data(&mut rdr)?)?),
+            1 => BundleReference::Activity(VoiceActivity::try_from(read_data(&mut rdr)?)?),
+            code => return Err(invalid(format!("unknown reference kind (code={})", code)))
+        };
+        let ref_fps = rdr.read_f64::<LittleEndian>()?;
+
+        let interval = rdr.read_i64::<LittleEndian>()?;
+        let split_mode = rdr.read_u8()? != 0;
+        let split_penalty = rdr.read_f64::<LittleEndian>()?;
+        let split_penalty_auto = rdr.read_u8()? != 0;
+        let speed_optimization = read_f64(&mut rdr)?;
+        let framerate_correction = rdr.read_u8()? != 0;
+        let has_time_budget = rdr.read_u8()? != 0;
+        let time_budget_millis = rdr.read_u64::<LittleEndian>()?;
+        let time_budget = if has_time_budget { Some(Duration::from_millis(time_budget_millis)) } else { None };
+        let min_confidence = read_f64(&mut rdr)?;
+        let reject_unusable_reference = rdr.read_u8()? != 0;
+        let options = SyncOptions {
+            interval, split_mode, split_penalty, split_penalty_auto, speed_optimization,
+            framerate_correction, time_budget, min_confidence, reject_unusable_reference, record_bundle: None
+        };
+
+        let outcome = match rdr.read_u8()? {
+            0 => {
+                let confidence = rdr.read_f64::<LittleEndian>()?;
+                SyncOutcome::Synced { confidence, output: read_data(&mut rdr)?.to_vec() }
+            },
+            1 => SyncOutcome::Failed { kind: read_str(&mut rdr)?.unwrap_or_default(), msg: read_str(&mut rdr)?.unwrap_or_default() },
+            code => return Err(invalid(format!("unknown outcome kind (code={})", code)))
+        };
+
+        Ok(SyncBundle {
+            library_version, sub_filename, sub_data, sub_encoding, detected_encoding, sub_format,
+            reference, ref_fps, options, outcome
+        })
+    }
+}
+
+///
+/// Represents an error reading, writing or replaying a sync bundle
+/// 
+#[derive(Debug)]
+pub enum BundleError {
+    FileNotFound { path: String },
+    ReadError { cause: io::Error, path: String },
+    DeserializeError { cause: io::Error },
+    SerializeError { cause: io::Error },
+    WriteError { cause: io::Error, path: String }
+}
+
+impl Error for BundleError {}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileNotFound { path } => write!(f, "Bundle file does not exist (path='{}')", path),
+            ReadError { cause, path } => write!(f, "Error reading bundle from disk (msg='{}', path='{}')", cause, path),
+            DeserializeError { cause } => write!(f, "Error parsing bundle from bytes (msg='{}')", cause),
+            SerializeError { cause } => write!(f, "Error serializing bundle to bytes (msg='{}')", cause),
+            WriteError { cause, path } => write!(f, "Error writing bundle data to disk (msg='{}', path='{}')", cause, path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use subparse::timetypes::{TimeSpan as SubTimeSpan, TimePoint as SubTimePoint};
+
+    use crate::{Coverage, VadConfig};
+
+    fn span(start: i64, end: i64) -> SubTimeSpan {
+        SubTimeSpan::new(SubTimePoint::from_msecs(start), SubTimePoint::from_msecs(end))
+    }
+
+    fn synced(output: &str) -> SyncOutcome {
+        SyncOutcome::Synced { output: output.as_bytes().to_vec(), confidence: 0.75 }
+    }
+
+    fn bundle(reference: BundleReference, outcome: SyncOutcome) -> SyncBundle {
+        SyncBundle {
+            library_version: LIBRARY_VERSION.to_string(),
+            sub_filename: "movie.srt".to_string(),
+            sub_data: b"1\n00:00:01,000 --> 00:00:02,000\nHello\n".to_vec(),
+            sub_encoding: Some("windows-1252".to_string()),
+            detected_encoding: "utf-8".to_string(),
+            sub_format: Some("SubRip (.srt)".to_string()),
+            reference,
+            ref_fps: 23.976,
+            options: SyncOptions {
+                interval: 20,
+                split_mode: false,
+                split_penalty: 4.5,
+                split_penalty_auto: true,
+                speed_optimization: None,
+                framerate_correction: true,
+                time_budget: Some(Duration::from_millis(1500)),
+                min_confidence: Some(0.4),
+                reject_unusable_reference: false,
+                record_bundle: None
+            },
+            outcome
+        }
+    }
+
+    fn round_trip(original: &SyncBundle) -> SyncBundle {
+        let bytes: Vec<u8> = original.try_into().unwrap();
+        SyncBundle::try_from(bytes.as_slice()).unwrap()
+    }
+
+    fn assert_same_header(loaded: &SyncBundle, original: &SyncBundle) {
+        assert_eq!(loaded.library_version, original.library_version);
+        assert_eq!(loaded.sub_filename, original.sub_filename);
+        assert_eq!(loaded.sub_data, original.sub_data);
+        assert_eq!(loaded.sub_encoding, original.sub_encoding);
+        assert_eq!(loaded.detected_encoding, original.detected_encoding);
+        assert_eq!(loaded.sub_format, original.sub_format);
+        assert_eq!(loaded.ref_fps, original.ref_fps);
+        assert_eq!(loaded.outcome, original.outcome);
+
+        let (o, l) = (&original.options, &loaded.options);
+        assert_eq!(l.interval, o.interval);
+        assert_eq!(l.split_mode, o.split_mode);
+        assert_eq!(l.split_penalty, o.split_penalty);
+        assert_eq!(l.split_penalty_auto, o.split_penalty_auto);
+        assert_eq!(l.speed_optimization, o.speed_optimization);
+        assert_eq!(l.framerate_correction, o.framerate_correction);
+        assert_eq!(l.time_budget, o.time_budget);
+        assert_eq!(l.min_confidence, o.min_confidence);
+        assert_eq!(l.reject_unusable_reference, o.reject_unusable_reference);
+        assert_eq!(l.record_bundle, None);
+    }
+
+    #[test]
+    fn round_trips_spans_reference_with_synced_outcome() {
+        let spans = TimeSpans::with_coverage(vec![span(1000, 2000), span(3000, 4500)], Coverage::Partial(vec![span(0, 5000)]));
+        let original = bundle(BundleReference::Spans(spans), synced("1\n00:00:01,500 --> 00:00:02,500\nHello\n"));
+        let loaded = round_trip(&original);
+        assert_same_header(&loaded, &original);
+        match &loaded.reference {
+            BundleReference::Spans(spans) => {
+                assert_eq!(spans.spans(), &[span(1000, 2000), span(3000, 4500)]);
+                assert_eq!(spans.coverage(), &Coverage::Partial(vec![span(0, 5000)]));
+            },
+            BundleReference::Activity(_) => panic!("expected spans reference")
+        }
+    }
+
+    #[test]
+    fn round_trips_activity_reference_with_failed_outcome() {
+        let activity = VoiceActivity {
+            data: vec![false, true, true, false],
+            likelihood: vec![0.2, 0.8, 0.9, 0.1],
+            known: vec![true; 4],
+            music: vec![],
+            start_millis: 500,
+            chunk_millis: 30,
+            vad_config: VadConfig::default(),
+            source: None
+        };
+        let outcome = SyncOutcome::Failed { kind: "LowConfidence".to_string(), msg: "confidence too low".to_string() };
+        let original = bundle(BundleReference::Activity(activity), outcome);
+        let loaded = round_trip(&original);
+        assert_same_header(&loaded, &original);
+        match &loaded.reference {
+            BundleReference::Activity(activity) => {
+                assert_eq!(activity.data, vec![false, true, true, false]);
+                assert_eq!(activity.likelihood, vec![0.2, 0.8, 0.9, 0.1]);
+                assert_eq!(activity.start_millis, 500);
+                assert_eq!(activity.chunk_millis, 30);
+            },
+            BundleReference::Spans(_) => panic!("expected activity reference")
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_bundle() {
+        let original = bundle(BundleReference::Spans(TimeSpans::new(vec![span(0, 1000)])), synced("Hello\n"));
+        let bytes: Vec<u8> = (&original).try_into().unwrap();
+        let err = SyncBundle::try_from(&bytes[.. bytes.len() - 1]).err().unwrap();
+        assert_eq!(err.kind(), InvalidData);
+        assert!(err.to_string().starts_with("truncated bundle"));
+        for len in 0 .. bytes.len() {
+            assert!(SyncBundle::try_from(&bytes[.. len]).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let original = bundle(BundleReference::Spans(TimeSpans::new(vec![])), synced(""));
+        let mut bytes: Vec<u8> = (&original).try_into().unwrap();
+        bytes[4 .. 6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        let err = SyncBundle::try_from(bytes.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), InvalidData);
+        assert_eq!(err.to_string(), format!("unsupported bundle version (version={}, supported={})", VERSION + 1, VERSION));
+    }
+
+    #[test]
+    fn no_differences_between_identical_outcomes() {
+        assert!(differences(&synced("a\nb\n"), &synced("a\nb\n")).is_empty());
+        let failed = SyncOutcome::Failed { kind: "LowConfidence".to_string(), msg: "confidence too low".to_string() };
+        assert!(differences(&failed, &failed).is_empty());
+    }
+
+    #[test]
+    fn lists_changed_line() {
+        assert_eq!(differences(&synced("a\nb\nc\n"), &synced("a\nx\nc\n")), vec!["line 2 differs (recorded='b', replayed='x')"]);
+    }
+
+    #[test]
+    fn limits_listed_lines() {
+        let recorded: String = (0 .. MAX_LINE_DIFFERENCES + 5).map(|i| format!("{}\n", i)).collect();
+        let replayed: String = (0 .. MAX_LINE_DIFFERENCES + 5).map(|i| format!("{}!\n", i)).collect();
+        let differences = differences(&synced(&recorded), &synced(&replayed));
+        assert_eq!(differences.len(), MAX_LINE_DIFFERENCES + 1);
+        assert_eq!(differences[0], "line 1 differs (recorded='0', replayed='0!')");
+        assert_eq!(differences[MAX_LINE_DIFFERENCES], "5 more lines differ");
+    }
+
+    #[test]
+    fn reports_line_ending_differences() {
+        assert_eq!(differences(&synced("a\nb\n"), &synced("a\r\nb\r\n")), vec!["output differs in line endings or invalid characters"]);
+    }
+}
@@ -0,0 +1,104 @@
+#![allow(clippy::needless_arbitrary_self_type)]
+
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+
+use log::{error, info};
+
+use crate::VoiceActivity;
+
+/// Number of capture sessions started by this process, telling apart sessions started
+/// within the same second
+static SESSION_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+///
+/// Capture of the audio and voice-activity processed by an `AudioSink` to disk, for
+/// debugging (see `AudioSink::start_capture`)
+/// 
+/// Files are named after the session so that captures of several sinks or runs do not
+/// overwrite each other. The first write error is logged and ends the capture without
+/// affecting the sink.
+/// 
+pub(crate) struct Capture {
+    session: String,
+    dir: PathBuf,
+    samples: BufWriter<File>,
+    vad: BufWriter<File>,
+    failed: bool
+}
+
+impl Capture {
+
+    ///
+    /// Starts a new session writing to files in the given directory
+    /// 
+    pub fn start(dir: &Path) -> Result<Self, io::Error> {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let session = format!("alass-{}-{}-{}", secs, std::process::id(), SESSION_COUNT.fetch_add(1, Ordering::Relaxed));
+        let create = |suffix: &str| File::create(dir.join(format!("{}-{}", session, suffix))).map(BufWriter::new);
+        let samples = create("samples.raw")?;
+        let vad = create("vad.raw")?;
+        info!("Capturing audio and voice-activity (session='{}', dir='{}')", session, dir.display());
+        Ok(Capture { session, dir: dir.to_path_buf(), samples, vad, failed: false })
+    }
+
+    pub fn session(self: &Self) -> &str {
+        &self.session
+    }
+
+    pub fn dir(self: &Self) -> &Path {
+        &self.dir
+    }
+
+    ///
+    /// Appends a chunk of samples as 16-bit little-endian (see `AudioSink::load_sample_data`)
+    /// 
+    pub fn write_samples(self: &mut Self, samples: &[i16]) {
+        if !self.failed {
+            let result = samples.iter().try_for_each(|s| self.samples.write_i16::<LittleEndian>(*s));
+            self.check(result);
+        }
+    }
+
+    ///
+    /// Appends the voice-activity of a chunk as a single byte (see `AudioSink::load_vad_data`)
+    /// 
+    pub fn write_vad(self: &mut Self, is_voice: bool) {
+        if !self.failed {
+            let result = self.vad.write_all(&[is_voice as u8]);
+            self.check(result);
+        }
+    }
+
+    ///
+    /// Ends the session, flushing files and saving the resulting voice-activity along with
+    /// its settings if given (see `VoiceActivity::save`)
+    /// 
+    pub fn finish(mut self: Self, activity: Option<&VoiceActivity>) {
+        if self.failed {
+            return;
+        }
+        let result = self.samples.flush().and_then(|_| self.vad.flush());
+        self.check(result);
+        if let Some(activity) = activity {
+            let path = self.dir.join(format!("{}-activity.bin", self.session));
+            if let Err(e) = activity.save(&path.to_string_lossy()) {
+                error!("Error capturing voice-activity (session='{}', msg='{}')", self.session, e);
+            }
+        }
+        info!("Finished capturing audio and voice-activity (session='{}')", self.session);
+    }
+
+    fn check(self: &mut Self, result: Result<(), io::Error>) {
+        if let Err(e) = result {
+            error!("Error capturing audio and voice-activity, capture stopped (session='{}', msg='{}')", self.session, e);
+            self.failed = true;
+        }
+    }
+}
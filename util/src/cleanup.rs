@@ -0,0 +1,81 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::{error::Error, fmt};
+
+use crate::ActivityRuns;
+
+use CleanupError::*;
+
+///
+/// Cleanup of voice-activity expressed in milliseconds, independent of the chunk length
+/// 
+/// Steps are applied in the order listed and each is disabled when zero (the default).
+/// Durations are rounded to the nearest whole number of chunks.
+/// 
+/// * `max_gap_millis`: Gaps between speech spans up to this long are bridged, joining
+///    syllables and words into utterances.
+/// 
+/// * `min_speech_millis`: Speech spans shorter than this are removed as noise once gaps
+///    have been bridged.
+/// 
+/// * `onset_millis`: Each speech span is extended by this much before its start, making up
+///    for the quiet onset of speech which detectors tend to miss.
+/// 
+/// * `offset_millis`: Each speech span is extended by this much past its end (hangover),
+///    making up for trailing consonants and fading voices.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Cleanup {
+    pub max_gap_millis: u64,
+    pub min_speech_millis: u64,
+    pub onset_millis: u64,
+    pub offset_millis: u64
+}
+
+impl Cleanup {
+
+    ///
+    /// Applies the cleanup to run-length encoded voice-activity
+    /// 
+    /// Fails if the chunk length is zero or any enabled duration is shorter than a chunk,
+    /// as such a duration cannot be represented.
+    /// 
+    pub fn apply(self: &Self, runs: &ActivityRuns) -> Result<ActivityRuns, CleanupError> {
+        let chunk_millis = runs.chunk_millis;
+        if chunk_millis == 0 {
+            return Err(InvalidChunkLength);
+        }
+        let chunks = |param: &'static str, millis: u64| {
+            if millis > 0 && millis < chunk_millis {
+                Err(DurationTooShort { param, millis, chunk_millis })
+            } else {
+                Ok((millis.saturating_add(chunk_millis / 2) / chunk_millis) as usize)
+            }
+        };
+        let max_gap = chunks("max_gap_millis", self.max_gap_millis)?;
+        let min_speech = chunks("min_speech_millis", self.min_speech_millis)?;
+        let onset = chunks("onset_millis", self.onset_millis)?;
+        let offset = chunks("offset_millis", self.offset_millis)?;
+        Ok(runs.bridge_gaps(max_gap).drop_shorter(min_speech).extend(onset, offset))
+    }
+}
+
+///
+/// Represents invalid cleanup settings
+/// 
+#[derive(Debug)]
+pub enum CleanupError {
+    InvalidChunkLength,
+    DurationTooShort { param: &'static str, millis: u64, chunk_millis: u64 }
+}
+
+impl Error for CleanupError {}
+
+impl fmt::Display for CleanupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidChunkLength => write!(f, "Cannot clean voice-activity with a chunk length of zero"),
+            DurationTooShort { param, millis, chunk_millis } => write!(f, "Cleanup duration is shorter than a chunk (param={}, millis={}, chunk_millis={})", param, millis, chunk_millis)
+        }
+    }
+}
@@ -0,0 +1,246 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use alass_core::{TimeSpan, TimePoint, TimeDelta};
+
+use subparse::timetypes::{TimeSpan as SubTimeSpan, TimePoint as SubTimePoint};
+
+use crate::util::to_alass_timepoint;
+
+///
+/// Regions of the timeline whose audio is known
+/// 
+/// * `Full`: The whole timeline is known. Regions without speech are silent.
+/// 
+/// * `Partial`: Only the given regions are known, e.g. when only several windows of the audio
+///    were decoded to save time (see `sample_windows`). Nothing is known about speech outside
+///    of these regions. Alignment, confidence and diagnosis disregard unknown regions.
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Coverage {
+    Full,
+    Partial(Vec<SubTimeSpan>)
+}
+
+impl Coverage {
+
+    ///
+    /// Derives coverage from a per-chunk mask of known chunks, merging adjacent known chunks
+    /// into regions. Coverage is `Full` if every chunk is known.
+    /// 
+    pub fn from_mask(known: &[bool], start_millis: i64, chunk_millis: u64) -> Self {
+        if known.iter().all(|k| *k) {
+            return Coverage::Full;
+        }
+        let chunk_time = |i: usize| SubTimePoint::from_msecs(start_millis + i as i64 * chunk_millis as i64);
+        let mut regions = Vec::new();
+        let mut region_start = None;
+        for (i, k) in known.iter().enumerate() {
+            match (region_start, *k) {
+                (None, true) => region_start = Some(i),
+                (Some(start), false) => {
+                    regions.push(SubTimeSpan::new(chunk_time(start), chunk_time(i)));
+                    region_start = None;
+                },
+                _ => ()
+            }
+        }
+        if let Some(start) = region_start {
+            regions.push(SubTimeSpan::new(chunk_time(start), chunk_time(known.len())));
+        }
+        Coverage::Partial(regions)
+    }
+
+    ///
+    /// Whether the whole timeline is known
+    /// 
+    pub fn is_full(self: &Self) -> bool {
+        matches!(self, Coverage::Full)
+    }
+
+    ///
+    /// Total duration of known regions (millis), or `None` if the whole timeline is known
+    /// 
+    pub fn known_millis(self: &Self) -> Option<i64> {
+        match self {
+            Coverage::Full => None,
+            Coverage::Partial(regions) => Some(regions.iter().map(|r| (r.end - r.start).msecs().max(0)).sum())
+        }
+    }
+
+    ///
+    /// Marks the given region as known. Coverage becomes `Partial` if it was `Full`.
+    /// 
+    pub fn push(self: &mut Self, region: SubTimeSpan) {
+        match self {
+            Coverage::Full => *self = Coverage::Partial(vec![region]),
+            Coverage::Partial(regions) => regions.push(region)
+        }
+    }
+
+    ///
+    /// Converts known regions to `alass_core` representation, or `None` if the whole timeline
+    /// is known
+    /// 
+    pub fn to_alass_timespans(self: &Self, interval: i64) -> Option<Vec<TimeSpan>> {
+        match self {
+            Coverage::Full => None,
+            Coverage::Partial(regions) => Some(regions.iter()
+                .map(|r| TimeSpan::new_safe(to_alass_timepoint(r.start, interval), to_alass_timepoint(r.end, interval)))
+                .collect())
+        }
+    }
+}
+
+///
+/// Chooses windows of audio to decode when decoding the whole track would take too long
+/// 
+/// Returns `count` windows of `window_millis` spread evenly across `duration_millis`, with
+/// equal gaps between them and half a gap before the first and after the last. Returns a
+/// single window spanning the whole duration if the windows would cover all of it. The
+/// resulting reference has `Partial` coverage (see `AudioSink::set_timestamp`).
+/// 
+/// Alignment improves with the number and length of windows. Windows should be long enough
+/// to hold several lines of dialogue each (at least a minute), and cover at least a tenth of
+/// the duration for reliable results.
+/// 
+pub fn sample_windows(duration_millis: i64, window_millis: i64, count: usize) -> Vec<SubTimeSpan> {
+    let span = |start: i64, end: i64| SubTimeSpan::new(SubTimePoint::from_msecs(start), SubTimePoint::from_msecs(end));
+    if duration_millis <= 0 || window_millis <= 0 || count == 0 {
+        return Vec::new();
+    }
+    let total_gap = duration_millis - window_millis * count as i64;
+    if total_gap <= 0 {
+        return vec![span(0, duration_millis)];
+    }
+    (0 .. count as i64)
+        .map(|i| {
+            let start = total_gap * (2 * i + 1) / (2 * count as i64) + i * window_millis;
+            span(start, start + window_millis)
+        })
+        .collect()
+}
+
+///
+/// Maps spans onto a timeline consisting only of the known regions, placed back to back
+/// 
+/// Spans are clipped to known regions, with any parts in unknown regions dropped. Used to
+/// evaluate alignments of partially covered references without unknown regions counting as
+/// silence (see `scoring::confidence`).
+/// 
+pub(crate) fn collapse_unknown(known: &[TimeSpan], spans: &[TimeSpan]) -> Vec<TimeSpan> {
+    let mut regions = known.to_vec();
+    regions.sort_by(|a, b| a.cmp_start(*b));
+
+    let mut collapsed = Vec::with_capacity(spans.len());
+    let mut offset = 0;
+    for region in regions.iter() {
+        let (start, end) = (region.start.as_i64(), region.end.as_i64());
+        for span in spans.iter() {
+            let (s, e) = (span.start.as_i64().max(start), span.end.as_i64().min(end));
+            if s < e {
+                collapsed.push(TimeSpan::new(TimePoint::from(s - start + offset), TimePoint::from(e - start + offset)));
+            }
+        }
+        offset += end - start;
+    }
+    collapsed
+}
+
+///
+/// Reference and subtitle spans mapped onto the timeline of known regions (see
+/// `collapse_unknown`), so that split-mode alignment ignores unknown regions
+/// 
+/// Subtitle lines are placed by an `anchor` shift aligning them as a whole, and each line is
+/// represented by its largest part within a known region. Lines outside of known regions do
+/// not take part in alignment and follow the nearest line that does (see `expand`).
+/// 
+pub(crate) struct CollapsedSpans {
+    pub ref_spans: Vec<TimeSpan>,
+    pub sub_spans: Vec<TimeSpan>,
+    lines: Vec<Option<usize>>,
+    starts: Vec<i64>,
+    anchor: TimeDelta
+}
+
+impl CollapsedSpans {
+
+    ///
+    /// Maps spans onto known regions, with subtitle lines shifted by `anchor`
+    /// 
+    pub fn new(known: &[TimeSpan], ref_spans: &[TimeSpan], sub_spans: &[TimeSpan], anchor: TimeDelta) -> Self {
+        let mut regions = known.to_vec();
+        regions.sort_by(|a, b| a.cmp_start(*b));
+
+        // Offset of each region on the collapsed timeline
+        let offsets: Vec<i64> = regions.iter()
+            .scan(0, |offset, r| {
+                let start = *offset;
+                *offset += r.len().as_i64();
+                Some(start)
+            })
+            .collect();
+
+        let mut collapsed = Vec::new();
+        let lines = sub_spans.iter()
+            .map(|span| {
+                let span = *span + anchor;
+                let (s, e, _) = regions.iter().zip(offsets.iter())
+                    .map(|(r, offset)| {
+                        let (start, end) = (r.start.as_i64(), r.end.as_i64());
+                        let (s, e) = (span.start.as_i64().max(start), span.end.as_i64().min(end));
+                        (s - start + offset, e - start + offset, e - s)
+                    })
+                    .max_by_key(|(_, _, len)| *len)
+                    .filter(|(_, _, len)| *len > 0)?;
+                collapsed.push(TimeSpan::new(TimePoint::from(s), TimePoint::from(e)));
+                Some(collapsed.len() - 1)
+            })
+            .collect();
+
+        let starts = sub_spans.iter().map(|s| s.start.as_i64()).collect();
+
+        CollapsedSpans { ref_spans: collapse_unknown(&regions, ref_spans), sub_spans: collapsed, lines, starts, anchor }
+    }
+
+    ///
+    /// Converts deltas of the collapsed subtitle spans to deltas of all original lines
+    /// 
+    /// Lines outside of known regions take the delta of the nearest line within them, as
+    /// nothing is known about where splits between the two fall. All lines are shifted by
+    /// the anchor if none lies within known regions.
+    /// 
+    pub fn expand(self: &Self, deltas: &[TimeDelta]) -> Vec<TimeDelta> {
+        let mut last = None;
+        let preceding: Vec<Option<usize>> = (0 .. self.lines.len())
+            .map(|l| {
+                if self.lines[l].is_some() {
+                    last = Some(l);
+                }
+                last
+            })
+            .collect();
+        let mut next = None;
+        let mut following: Vec<Option<usize>> = (0 .. self.lines.len()).rev()
+            .map(|l| {
+                if self.lines[l].is_some() {
+                    next = Some(l);
+                }
+                next
+            })
+            .collect();
+        following.reverse();
+
+        (0 .. self.lines.len())
+            .map(|l| {
+                let nearest = match (preceding[l], following[l]) {
+                    (Some(p), Some(f)) if self.starts[l] - self.starts[p] > self.starts[f] - self.starts[l] => Some(f),
+                    (p, f) => p.or(f)
+                };
+                match nearest.and_then(|n| self.lines[n]) {
+                    Some(i) => self.anchor + deltas[i],
+                    None => self.anchor
+                }
+            })
+            .collect()
+    }
+}
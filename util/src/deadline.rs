@@ -0,0 +1,50 @@
+#![allow(clippy::needless_arbitrary_self_type)]
+
+use std::time::{Duration, Instant};
+
+///
+/// Tracks the time spent within an optional time budget
+/// 
+/// Alignments run by `alass_core` cannot be interrupted, so the deadline is checked between
+/// the steps of a sync, deciding whether to start further work. A step started before the
+/// deadline runs to completion even if it overruns it.
+/// 
+#[derive(Clone, Copy)]
+pub struct Deadline {
+    start: Instant,
+    budget: Option<Duration>
+}
+
+impl Deadline {
+
+    ///
+    /// Starts the clock on a new deadline. If no budget is given the deadline never expires.
+    /// 
+    pub fn start(budget: Option<Duration>) -> Self {
+        Deadline { start: Instant::now(), budget }
+    }
+
+    ///
+    /// Time elapsed since the deadline was started
+    /// 
+    pub fn elapsed(self: &Self) -> Duration {
+        self.start.elapsed()
+    }
+
+    ///
+    /// Whether more than the given fraction of the time budget has been spent
+    /// 
+    pub fn spent(self: &Self, fraction: f64) -> bool {
+        match self.budget {
+            Some(b) => self.elapsed().as_secs_f64() > b.as_secs_f64() * fraction,
+            None => false
+        }
+    }
+
+    ///
+    /// Whether the whole time budget has been spent
+    /// 
+    pub fn expired(self: &Self) -> bool {
+        self.spent(1.0)
+    }
+}
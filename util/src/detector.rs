@@ -0,0 +1,209 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use webrtc_vad::Vad;
+
+use crate::{AudioSinkError, VadConfig, VadMode};
+use crate::AudioSinkError::*;
+
+/// Energy detector: lowest frame energy considered speech regardless of noise floor (dBFS)
+const ENERGY_FLOOR_DB: f64 = -55.0;
+
+/// Energy detector: rate at which the noise floor estimate rises towards louder frames (per second)
+const NOISE_FLOOR_RISE: f64 = 0.5;
+
+/// Energy detector: zero-crossing rate (crossings per sample) above which a frame is
+/// considered broadband noise rather than speech
+const MAX_SPEECH_ZCR: f64 = 0.35;
+
+/// Likelihood at or above which a frame is considered speech
+pub const SPEECH_LIKELIHOOD: f32 = 0.5;
+
+/// Energy detector: spread of the likelihood around the speech threshold (dB)
+const LIKELIHOOD_SPREAD_DB: f64 = 3.0;
+
+/// Energy detector: time speech is held after the last detected frame to bridge short
+/// pauses between syllables (millis)
+const HANGOVER_MILLIS: u64 = 120;
+
+///
+/// Estimates the likelihood of speech in frames of mono audio
+/// 
+/// Frames are passed in order and are exactly `VadConfig::frame_samples()` long at the
+/// rate given by `VadConfig::rate`, allowing implementations to track state across frames.
+/// 
+/// Likelihoods lie between 0 and 1. Frames at or above `SPEECH_LIKELIHOOD` are considered
+/// speech. Binary detectors report exactly 0 or 1.
+/// 
+/// `reset` is called when an `AudioSink` is reused for another stream and should clear any
+/// state carried across frames.
+/// 
+pub trait VoiceDetector {
+    fn likelihood(self: &mut Self, frame: &[i16]) -> Result<f32, AudioSinkError>;
+
+    fn reset(self: &mut Self) {}
+
+    fn is_voice(self: &mut Self, frame: &[i16]) -> Result<bool, AudioSinkError> {
+        Ok(self.likelihood(frame)? >= SPEECH_LIKELIHOOD)
+    }
+}
+
+impl VoiceDetector for Box<dyn VoiceDetector> {
+    fn likelihood(self: &mut Self, frame: &[i16]) -> Result<f32, AudioSinkError> {
+        (**self).likelihood(frame)
+    }
+
+    fn reset(self: &mut Self) {
+        (**self).reset()
+    }
+}
+
+///
+/// Voice-activity detector backends
+/// 
+/// * `WebRtc`: The voice-activity detector from WebRTC, a Gaussian mixture model over
+///    sub-band energies. Accurate on typical film and TV soundtracks.
+/// 
+/// * `Energy`: A pure-Rust detector comparing frame energy against an adaptive noise floor
+///    and rejecting noise-like frames by their zero-crossing rate. Several times faster than
+///    `WebRtc` but more easily fooled by music and effects.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VadBackend { WebRtc, Energy }
+
+impl VadBackend {
+
+    ///
+    /// Creates a detector for this backend with the given settings
+    /// 
+    pub fn detector(self: &Self, config: &VadConfig) -> Box<dyn VoiceDetector> {
+        match self {
+            VadBackend::WebRtc => Box::new(WebRtcDetector::new(config)),
+            VadBackend::Energy => Box::new(EnergyDetector::new(config))
+        }
+    }
+}
+
+///
+/// Voice-activity detector backed by `webrtc-vad`
+/// 
+/// The WebRTC detector only makes binary decisions. When `VadConfig::soft` is set, frames
+/// are classified at every `VadMode` and the likelihood is graded by how many modes detect
+/// speech relative to `VadConfig::mode`, so that likelihood thresholds above or below
+/// `SPEECH_LIKELIHOOD` correspond to more or less aggressive modes.
+/// 
+pub struct WebRtcDetector {
+    config: VadConfig,
+    vads: Vec<Vad>,
+    mode_index: usize
+}
+
+impl WebRtcDetector {
+    pub fn new(config: &VadConfig) -> Self {
+        let modes = if config.soft { VadMode::ALL.to_vec() } else { vec![config.mode] };
+        let mode_index = modes.iter().position(|m| *m == config.mode).unwrap_or(0);
+        let vads = modes.iter().map(|m| Vad::new_with_rate_and_mode(config.rate.webrtc(), m.webrtc())).collect();
+        WebRtcDetector { config: *config, vads, mode_index }
+    }
+}
+
+impl VoiceDetector for WebRtcDetector {
+    fn likelihood(self: &mut Self, frame: &[i16]) -> Result<f32, AudioSinkError> {
+        let mut detections = 0;
+        for vad in self.vads.iter_mut() {
+            if vad.is_voice_segment(frame).map_err(|_| VoiceDetectionError)? {
+                detections += 1;
+            }
+        }
+        if self.vads.len() == 1 {
+            Ok(detections as f32)
+        } else {
+            // Modes are ordered by aggressiveness so `detections > mode_index` iff the configured mode detects speech
+            let spread = self.vads.len() as f32 * 2.0 - 1.0;
+            let likelihood = SPEECH_LIKELIHOOD + (detections as f32 - self.mode_index as f32 - 0.5) / spread;
+            Ok(likelihood.clamp(0.0, 1.0))
+        }
+    }
+
+    fn reset(self: &mut Self) {
+        // `Vad::reset` also reverts mode and rate, so start over instead
+        *self = WebRtcDetector::new(&self.config);
+    }
+}
+
+///
+/// Pure-Rust voice-activity detector based on frame energy and zero-crossing rate
+/// 
+/// A frame is considered speech when its energy exceeds the tracked noise floor by a margin
+/// determined by `VadConfig::mode` and its zero-crossing rate is typical of speech. The
+/// likelihood grows smoothly with the energy above the margin. Speech is held for a short
+/// while after the last detected frame to bridge pauses between syllables.
+/// 
+pub struct EnergyDetector {
+    margin_db: f64,
+    noise_rise: f64,
+    hangover_frames: u64,
+    noise_db: Option<f64>,
+    hangover: u64
+}
+
+impl EnergyDetector {
+    pub fn new(config: &VadConfig) -> Self {
+        let margin_db = match config.mode {
+            VadMode::Quality => 6.0,
+            VadMode::LowBitrate => 9.0,
+            VadMode::Aggressive => 12.0,
+            VadMode::VeryAggressive => 15.0
+        };
+        let frame_millis = config.frame.millis();
+        EnergyDetector {
+            margin_db,
+            noise_rise: NOISE_FLOOR_RISE * frame_millis as f64 / 1000.0,
+            hangover_frames: HANGOVER_MILLIS / frame_millis,
+            noise_db: None,
+            hangover: 0
+        }
+    }
+}
+
+impl VoiceDetector for EnergyDetector {
+    fn likelihood(self: &mut Self, frame: &[i16]) -> Result<f32, AudioSinkError> {
+        if frame.is_empty() {
+            return Err(VoiceDetectionError);
+        }
+
+        let power = frame.iter().map(|s| { let s = *s as f64 / 32768.0; s * s }).sum::<f64>() / frame.len() as f64;
+        let energy_db = 10.0 * power.max(1e-10).log10();
+        let crossings = frame.windows(2).filter(|w| (w[0] >= 0) != (w[1] >= 0)).count();
+        let zcr = crossings as f64 / frame.len() as f64;
+
+        // Track noise floor: follow quieter frames immediately, louder frames slowly
+        let noise_db = match self.noise_db {
+            Some(n) if energy_db < n => energy_db,
+            Some(n) => n + (energy_db - n) * self.noise_rise,
+            None => energy_db
+        };
+        self.noise_db = Some(noise_db);
+
+        let likelihood = if energy_db > ENERGY_FLOOR_DB && zcr < MAX_SPEECH_ZCR {
+            let excess_db = energy_db - noise_db - self.margin_db;
+            (1.0 / (1.0 + (-excess_db / LIKELIHOOD_SPREAD_DB).exp())) as f32
+        } else {
+            0.0
+        };
+
+        if likelihood >= SPEECH_LIKELIHOOD {
+            self.hangover = self.hangover_frames;
+            Ok(likelihood)
+        } else if self.hangover > 0 {
+            self.hangover -= 1;
+            Ok(SPEECH_LIKELIHOOD)
+        } else {
+            Ok(likelihood)
+        }
+    }
+
+    fn reset(self: &mut Self) {
+        self.noise_db = None;
+        self.hangover = 0;
+    }
+}
@@ -0,0 +1,163 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::fmt;
+
+use crate::{TimeSpans, Coverage};
+
+use ReferenceIssue::*;
+
+/// Minimum number of spans for a reference to be considered usable
+const MIN_SPAN_COUNT: usize = 10;
+
+/// Minimum fraction of the reference which must be covered by spans
+const MIN_COVERAGE_RATIO: f64 = 0.02;
+
+/// Maximum fraction of the reference which may be covered by spans. References above
+/// this are typically the result of noise or music being detected as speech.
+const MAX_COVERAGE_RATIO: f64 = 0.9;
+
+///
+/// Statistics describing the quality of reference timespans (see `TimeSpans::diagnose`)
+/// 
+/// * `span_count`: Number of spans.
+/// 
+/// * `duration`: Time from the start of the first span to the end of the last, or the total
+///    duration of known regions if partially covered (see `Coverage`). (millis)
+/// 
+/// * `coverage`: Total time covered by spans, counting overlapping spans once. (millis)
+/// 
+/// * `coverage_ratio`: Fraction of `duration` covered by spans.
+/// 
+/// * `median_span_length`: Median length of a span. (millis)
+/// 
+/// * `longest_silence`: Longest gap between consecutive spans within a known region. (millis)
+/// 
+/// * `issues`: Conditions that make the reference unusable for syncing.
+/// 
+#[derive(Debug, Clone)]
+pub struct TimeSpansDiagnosis {
+    pub span_count: usize,
+    pub duration: i64,
+    pub coverage: i64,
+    pub coverage_ratio: f64,
+    pub median_span_length: i64,
+    pub longest_silence: i64,
+    pub issues: Vec<ReferenceIssue>
+}
+
+impl TimeSpansDiagnosis {
+
+    ///
+    /// Whether the reference is suitable for syncing
+    /// 
+    pub fn is_usable(self: &Self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for TimeSpansDiagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TimeSpansDiagnosis(span_count={}, duration={}ms, coverage={}ms, coverage_ratio={:.3}, median_span_length={}ms, longest_silence={}ms, issues=[{}])",
+            self.span_count, self.duration, self.coverage, self.coverage_ratio, self.median_span_length, self.longest_silence,
+            self.issues.iter().map(|i| i.to_string()).collect::<Vec<String>>().join(", "))
+    }
+}
+
+///
+/// A condition making reference timespans unusable for syncing
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReferenceIssue {
+    /// The reference contains no spans (e.g. the audio was silent)
+    Empty,
+
+    /// The reference contains too few spans to align against
+    TooFewSpans,
+
+    /// Spans cover too little of the reference to align against
+    TooSparse,
+
+    /// Spans cover nearly all of the reference (e.g. noise or music detected as speech)
+    MostlySpeech
+}
+
+impl fmt::Display for ReferenceIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Empty => write!(f, "empty"),
+            TooFewSpans => write!(f, "too few spans"),
+            TooSparse => write!(f, "too sparse"),
+            MostlySpeech => write!(f, "mostly speech")
+        }
+    }
+}
+
+impl TimeSpans {
+
+    ///
+    /// Computes statistics describing the quality of the timespans and flags conditions
+    /// which make them unusable as a reference for syncing
+    /// 
+    pub fn diagnose(self: &Self) -> TimeSpansDiagnosis {
+        let mut spans: Vec<(i64, i64)> = self.spans().iter()
+            .map(|s| (s.start.msecs().min(s.end.msecs()), s.start.msecs().max(s.end.msecs())))
+            .collect();
+        spans.sort_unstable();
+
+        // Merge overlapping spans
+        let mut merged: Vec<(i64, i64)> = Vec::with_capacity(spans.len());
+        for &(start, end) in spans.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end))
+            }
+        }
+
+        // Only known regions count towards duration and silences of partially covered spans
+        let duration = match (self.coverage().known_millis(), merged.first(), merged.last()) {
+            (Some(known), _, _) => known,
+            (None, Some(first), Some(last)) => last.1 - first.0,
+            _ => 0
+        };
+        let is_known = |start: i64, end: i64| match self.coverage() {
+            Coverage::Full => true,
+            Coverage::Partial(regions) => regions.iter().any(|r| r.start.msecs() <= start && end <= r.end.msecs())
+        };
+        let coverage: i64 = merged.iter().map(|(start, end)| end - start).sum();
+        let coverage_ratio = if duration > 0 { coverage as f64 / duration as f64 } else { 0.0 };
+        let longest_silence = merged.windows(2)
+            .filter(|w| is_known(w[0].1, w[1].0))
+            .map(|w| w[1].0 - w[0].1)
+            .max()
+            .unwrap_or(0);
+
+        let mut lengths: Vec<i64> = spans.iter().map(|(start, end)| end - start).collect();
+        lengths.sort_unstable();
+        let median_span_length = lengths.get(lengths.len() / 2).cloned().unwrap_or(0);
+
+        let mut issues = Vec::new();
+        if spans.is_empty() {
+            issues.push(Empty);
+        } else {
+            if spans.len() < MIN_SPAN_COUNT {
+                issues.push(TooFewSpans);
+            }
+            if coverage_ratio < MIN_COVERAGE_RATIO {
+                issues.push(TooSparse);
+            }
+            if coverage_ratio > MAX_COVERAGE_RATIO {
+                issues.push(MostlySpeech);
+            }
+        }
+
+        TimeSpansDiagnosis {
+            span_count: spans.len(),
+            duration,
+            coverage,
+            coverage_ratio,
+            median_span_length,
+            longest_silence,
+            issues
+        }
+    }
+}
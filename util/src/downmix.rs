@@ -0,0 +1,73 @@
+#![allow(clippy::needless_arbitrary_self_type)]
+
+///
+/// Streaming mixer producing mono samples from interleaved multi-channel frames
+/// 
+/// Several mixes may be produced at once, in which case their samples are interleaved in
+/// the output. Frames split across successive calls are held back until complete.
+/// 
+pub struct Downmixer {
+    channels: usize,
+    mixes: Vec<Vec<f32>>,
+    partial: Vec<i16>
+}
+
+impl Downmixer {
+
+    ///
+    /// Creates a downmixer producing a single mix with the given per-channel weights
+    /// 
+    pub fn new(weights: Vec<f32>) -> Self {
+        Downmixer::with_mixes(vec![weights])
+    }
+
+    ///
+    /// Creates a downmixer producing a mix for each of the given sets of per-channel weights
+    /// 
+    pub fn with_mixes(mixes: Vec<Vec<f32>>) -> Self {
+        let channels = mixes.first().map(|w| w.len()).unwrap_or(0);
+        Downmixer { channels, partial: Vec::with_capacity(channels), mixes }
+    }
+
+    ///
+    /// Discards any incomplete frame held back from previous calls
+    /// 
+    pub fn reset(self: &mut Self) {
+        self.partial.clear();
+    }
+
+    ///
+    /// Mixes the given interleaved samples, appending complete frames to `output`
+    /// 
+    pub fn process(self: &mut Self, input: &[i16], output: &mut Vec<i16>) {
+        let channels = self.channels;
+
+        // Complete the frame left over from the previous call
+        let mut input = input;
+        if !self.partial.is_empty() {
+            let needed = (channels - self.partial.len()).min(input.len());
+            self.partial.extend_from_slice(&input[.. needed]);
+            input = &input[needed ..];
+            if self.partial.len() < channels {
+                return;
+            }
+            let frame = std::mem::take(&mut self.partial);
+            self.mix(&frame, output);
+            self.partial = frame;
+            self.partial.clear();
+        }
+
+        let frames = input.chunks_exact(channels);
+        self.partial.extend_from_slice(frames.remainder());
+        for frame in frames {
+            self.mix(frame, output);
+        }
+    }
+
+    fn mix(self: &Self, frame: &[i16], output: &mut Vec<i16>) {
+        output.extend(self.mixes.iter().map(|weights| {
+            let sample: f32 = frame.iter().zip(weights.iter()).map(|(&s, &w)| s as f32 * w).sum();
+            sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+        }));
+    }
+}
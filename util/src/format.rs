@@ -0,0 +1,299 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::ops::RangeInclusive;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::AudioSinkError;
+use crate::AudioSinkError::InvalidFormat;
+
+/// The range of input sample rates accepted by `AudioSink::with_format` (Hz)
+pub const SAMPLE_RATES: RangeInclusive<u32> = 4_000 ..= 384_000;
+
+/// The maximum number of interleaved channels accepted by `AudioSink::with_format`
+pub const MAX_CHANNELS: usize = 32;
+
+///
+/// Describes the audio sent to an `AudioSink`
+/// 
+/// * `sample_rate`: Sample rate of the incoming audio. (Hz)
+/// 
+/// * `channels`: Number of channels in each frame.
+/// 
+/// * `sample_format`: Encoding of samples sent as raw bytes (see `AudioSink::send_bytes`).
+///    Typed samples (see `AudioSink::send`) carry their own encoding.
+/// 
+/// * `layout`: Assignment of speaker positions to channels, used by `Downmix::Dialogue`
+///    and `Downmix::Fused` to locate the center and front channels.
+/// 
+/// * `downmix`: How multi-channel frames are mixed down to mono before voice-activity
+///    detection, or fused after detecting voice-activity on each channel. Ignored for
+///    mono audio.
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub sample_format: SampleFormat,
+    pub layout: ChannelLayout,
+    pub downmix: Downmix
+}
+
+impl AudioFormat {
+
+    ///
+    /// Describes mono audio at the given sample rate
+    /// 
+    pub fn mono(sample_rate: u32) -> Self {
+        AudioFormat::interleaved(sample_rate, 1)
+    }
+
+    ///
+    /// Describes 16-bit audio with the given number of channels, assuming the conventional
+    /// layout for that channel count (see `ChannelLayout::for_channels`) and a
+    /// dialogue-weighted downmix
+    /// 
+    pub fn interleaved(sample_rate: u32, channels: usize) -> Self {
+        AudioFormat {
+            sample_rate,
+            channels,
+            sample_format: SampleFormat::I16,
+            layout: ChannelLayout::for_channels(channels),
+            downmix: Downmix::Dialogue
+        }
+    }
+
+    ///
+    /// Ensures the format is supported and internally consistent
+    /// 
+    pub fn validate(self: &Self) -> Result<(), AudioSinkError> {
+        if !SAMPLE_RATES.contains(&self.sample_rate) {
+            return Err(AudioSinkError::UnsupportedSampleRate { rate: self.sample_rate });
+        }
+        if self.channels == 0 || self.channels > MAX_CHANNELS {
+            return Err(InvalidFormat { msg: format!("channel count must be between 1 and {} (channels={})", MAX_CHANNELS, self.channels) });
+        }
+        if let Some(n) = self.layout.channel_count() {
+            if n != self.channels {
+                return Err(InvalidFormat { msg: format!("layout {:?} requires {} channels (channels={})", self.layout, n, self.channels) });
+            }
+        }
+        if let Downmix::Channel(c) = self.downmix {
+            if c >= self.channels {
+                return Err(InvalidFormat { msg: format!("downmix channel out of range (channel={}, channels={})", c, self.channels) });
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// Per-channel weights used to mix a frame down to mono. Weights sum to one.
+    /// 
+    pub fn downmix_weights(self: &Self) -> Vec<f32> {
+        let n = self.channels;
+        let average = vec![1.0 / n as f32; n];
+        match self.downmix {
+            Downmix::Average => average,
+            Downmix::Channel(c) => (0 .. n).map(|i| if i == c { 1.0 } else { 0.0 }).collect(),
+            Downmix::Dialogue | Downmix::Fused(_) => match self.layout.dialogue_weights() {
+                Some(weights) => weights.to_vec(),
+                None => average
+            }
+        }
+    }
+
+    ///
+    /// Channels whose voice-activity is detected separately and fused (see `Downmix::Fused`),
+    /// or `None` if audio is mixed down to mono
+    /// 
+    pub fn fused_channels(self: &Self) -> Option<Vec<usize>> {
+        match self.downmix {
+            Downmix::Fused(_) => Some(match self.layout.dialogue_channels() {
+                Some(channels) => channels.to_vec(),
+                None => (0 .. self.channels).collect()
+            }),
+            _ => None
+        }
+    }
+}
+
+///
+/// Assignment of speaker positions to interleaved channels
+/// 
+/// Channels are expected in the conventional WAVE/SMPTE order:
+/// 
+/// * `Mono`: C
+/// * `Stereo`: L, R
+/// * `Surround51`: L, R, C, LFE, Ls, Rs
+/// * `Surround71`: L, R, C, LFE, Lb, Rb, Ls, Rs
+/// * `Unknown`: Any number of channels without a known layout
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelLayout { Mono, Stereo, Surround51, Surround71, Unknown }
+
+impl ChannelLayout {
+
+    ///
+    /// The conventional layout for the given channel count
+    /// 
+    pub fn for_channels(channels: usize) -> Self {
+        match channels {
+            1 => ChannelLayout::Mono,
+            2 => ChannelLayout::Stereo,
+            6 => ChannelLayout::Surround51,
+            8 => ChannelLayout::Surround71,
+            _ => ChannelLayout::Unknown
+        }
+    }
+
+    ///
+    /// Number of channels required by the layout (`None` if unknown)
+    /// 
+    pub fn channel_count(self: &Self) -> Option<usize> {
+        match self {
+            ChannelLayout::Mono => Some(1),
+            ChannelLayout::Stereo => Some(2),
+            ChannelLayout::Surround51 => Some(6),
+            ChannelLayout::Surround71 => Some(8),
+            ChannelLayout::Unknown => None
+        }
+    }
+
+    ///
+    /// Downmix weights favoring the channels which carry dialogue. Dialogue is mixed into
+    /// the center channel of surround audio while effects and music dominate the others.
+    /// 
+    fn dialogue_weights(self: &Self) -> Option<&'static [f32]> {
+        match self {
+            ChannelLayout::Mono => Some(&[ 1.0 ]),
+            ChannelLayout::Stereo => Some(&[ 0.5, 0.5 ]),
+            ChannelLayout::Surround51 => Some(&[ 0.2, 0.2, 0.6, 0.0, 0.0, 0.0 ]),
+            ChannelLayout::Surround71 => Some(&[ 0.2, 0.2, 0.6, 0.0, 0.0, 0.0, 0.0, 0.0 ]),
+            ChannelLayout::Unknown => None
+        }
+    }
+
+    ///
+    /// Indices of the center and front channels, which carry dialogue
+    /// 
+    fn dialogue_channels(self: &Self) -> Option<&'static [usize]> {
+        match self {
+            ChannelLayout::Mono => Some(&[ 0 ]),
+            ChannelLayout::Stereo => Some(&[ 0, 1 ]),
+            ChannelLayout::Surround51 | ChannelLayout::Surround71 => Some(&[ 0, 1, 2 ]),
+            ChannelLayout::Unknown => None
+        }
+    }
+}
+
+///
+/// Strategy for mixing multi-channel audio down to mono
+/// 
+/// * `Average`: Equal contribution from every channel.
+/// * `Channel`: Use only the channel at the given index.
+/// * `Dialogue`: Favor the center channel, where dialogue is mixed, over the others. Falls
+///    back to `Average` for unknown layouts.
+/// * `Fused`: Detect voice-activity on the center and front channels separately and combine
+///    the results with the given rule, so that effects panned to the sides do not mask
+///    dialogue. Every channel is analyzed for unknown layouts. Slower by the number of
+///    channels analyzed.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Downmix { Average, Channel(usize), Dialogue, Fused(ChannelFusion) }
+
+///
+/// Rule combining voice-activity detected on several channels (see `Downmix::Fused`)
+/// 
+/// * `Any`: Speech on any channel, e.g. center OR front. Catches dialogue mixed off-center
+///    at the risk of effects in the fronts being taken for speech.
+/// * `All`: Speech on every channel, e.g. center AND front. Fewest false positives, but
+///    misses dialogue mixed into the center only.
+/// * `Majority`: Speech on more than half of the channels. Equal to `All` for two channels.
+/// 
+/// Speech likelihoods are combined likewise, taking the highest, the lowest or the one
+/// ranked just past the middle of all channels respectively.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelFusion { Any, All, Majority }
+
+impl ChannelFusion {
+
+    ///
+    /// Combines the speech likelihoods of one chunk across channels
+    /// 
+    pub(crate) fn fuse(self: &Self, likelihoods: &mut [f32]) -> f32 {
+        if likelihoods.is_empty() {
+            return 0.0;
+        }
+        likelihoods.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        let rank = match self {
+            ChannelFusion::Any => 0,
+            ChannelFusion::All => likelihoods.len() - 1,
+            ChannelFusion::Majority => likelihoods.len() / 2
+        };
+        likelihoods[rank]
+    }
+}
+
+///
+/// Encoding of individual samples. Multi-byte formats are little-endian.
+/// 
+/// * `U8`: Unsigned 8-bit, centered at 128.
+/// * `I16`: Signed 16-bit.
+/// * `I32`: Signed 32-bit.
+/// * `F32`: 32-bit float, nominally within `[-1.0, 1.0]`. Values beyond are clipped.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleFormat { U8, I16, I32, F32 }
+
+impl SampleFormat {
+
+    ///
+    /// Size of a single sample in bytes
+    /// 
+    pub fn bytes(self: &Self) -> usize {
+        match self {
+            SampleFormat::U8 => 1,
+            SampleFormat::I16 => 2,
+            SampleFormat::I32 | SampleFormat::F32 => 4
+        }
+    }
+
+    ///
+    /// Decodes a single little-endian sample. `bytes` must be exactly `self.bytes()` long.
+    /// 
+    pub fn decode(self: &Self, bytes: &[u8]) -> i16 {
+        match self {
+            SampleFormat::U8 => bytes[0].to_i16(),
+            SampleFormat::I16 => LittleEndian::read_i16(bytes),
+            SampleFormat::I32 => LittleEndian::read_i32(bytes).to_i16(),
+            SampleFormat::F32 => LittleEndian::read_f32(bytes).to_i16()
+        }
+    }
+}
+
+///
+/// A sample type accepted by `AudioSink::send`, convertible to the 16-bit samples used
+/// internally for voice-activity detection
+/// 
+pub trait Sample: Copy {
+    fn to_i16(self) -> i16;
+}
+
+impl Sample for i16 {
+    fn to_i16(self) -> i16 { self }
+}
+
+impl Sample for u8 {
+    fn to_i16(self) -> i16 { (self as i16 - 128) << 8 }
+}
+
+impl Sample for i32 {
+    fn to_i16(self) -> i16 { (self >> 16) as i16 }
+}
+
+impl Sample for f32 {
+    fn to_i16(self) -> i16 {
+        if self.is_nan() { 0 } else { (self * 32767.0).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16 }
+    }
+}
@@ -1,6 +1,7 @@
 extern crate chardet;
 
 use std::{fmt, io, vec};
+use std::cell::Cell;
 use std::fs::File;
 use std::path::Path;
 use std::error::Error;
@@ -19,6 +20,26 @@ mod audiosink;
 mod timespans;
 mod voice_activity;
 mod options;
+mod report;
+mod deadline;
+mod scoring;
+mod ranking;
+mod diagnosis;
+mod resample;
+mod downmix;
+mod format;
+mod vad_config;
+mod detector;
+mod coverage;
+mod parallel;
+mod wav;
+mod preprocess;
+mod music;
+mod runs;
+mod cleanup;
+mod tuning;
+mod capture;
+mod bundle;
 
 ///
 /// Various utility functions for conversions, charset detection, etc.
@@ -32,11 +53,30 @@ pub mod morph;
 
 pub use audiosink::*;
 pub use timespans::*;
-pub use voice_activity::VoiceActivity;
+pub use voice_activity::{VoiceActivity, ActivitySource, VoiceActivityLoadError, VoiceActivitySaveError};
+pub use runs::ActivityRuns;
+pub use cleanup::{Cleanup, CleanupError};
+pub use tuning::{tune_cleanup, CleanupTuning};
 pub use options::SyncOptions;
+pub use report::{SyncReport, Degradation};
+pub use ranking::*;
+pub use diagnosis::*;
+pub use format::{AudioFormat, ChannelLayout, ChannelFusion, Downmix, SampleFormat, Sample};
+pub use vad_config::*;
+pub use detector::*;
+pub use coverage::{Coverage, sample_windows};
+pub use parallel::{parallel_voice_activity, parallel_voice_activity_bytes};
+pub use wav::{WavReader, WavError};
+pub use preprocess::{Preprocessing, BandPass};
+pub use bundle::{SyncBundle, BundleReference, SyncOutcome, BundleReplay, BundleError, replay_bundle};
+use deadline::Deadline;
+use bundle::record_sync;
+use scoring::{select_split_penalty, confidence};
+use coverage::CollapsedSpans;
 use util::*;
 
 use SyncError::*;
+use Degradation::*;
 
 /// Standard framerates for framerate correction
 pub const FRAMERATES: &[f64] = &[ 23.976023976024, 24.0, 25.0, 29.97002997003, 30.0, 50.0, 59.9400599400599, 60.0 ];
@@ -44,6 +84,17 @@ pub const FRAMERATES: &[f64] = &[ 23.976023976024, 24.0, 25.0, 29.97002997003, 3
 /// Default value used to interpret MicroDVD '.sub' files which use frame ids instead of timestamps
 pub const DEFAULT_MICRODVD_FPS: f64 = 30.0;
 
+/// Fraction of the time budget which may be spent evaluating candidate framerates
+const FRAMERATE_BUDGET_SHARE: f64 = 0.25;
+
+/// Fraction of the time budget after which split-mode alignment is started with a raised
+/// `speed_optimization`
+const SPEED_BUDGET_SHARE: f64 = 0.5;
+
+/// `speed_optimization` used for split-mode alignment once `SPEED_BUDGET_SHARE` of the time
+/// budget has been spent
+const RAISED_SPEED_OPTIMIZATION: f64 = 10.0;
+
 ///
 /// Reads and parses an input subtitle file, synchronizes it with the given reference timespans using the
 /// `alass` crate, and writes the corrected subtitles back to disk.
@@ -64,6 +115,14 @@ pub const DEFAULT_MICRODVD_FPS: f64 = 30.0;
 /// * `options`: Parameters governing various aspects of the synchronization process. See
 ///    `SyncOptions` or `alass` documentation for details.
 /// 
+/// Returns a `SyncReport` describing the alignment and any measures taken to stay within the
+/// time budget. The output file is only written once synchronization has completed, and is
+/// left untouched if the alignment confidence falls below `min_confidence`, in which case the
+/// report is returned with the `LowConfidence` error instead.
+/// 
+/// A bundle reproducing the sync is recorded if `record_bundle` is set (see `SyncBundle`).
+/// 
+#[allow(clippy::doc_overindented_list_items)]
 pub fn sync(
     sub_path_in: &str,
     sub_path_out: &str,
@@ -71,21 +130,70 @@ pub fn sync(
     ref_fps: f64,
     sub_encoding: Option<String>,
     opt: &SyncOptions
-) -> Result<(), SyncError> {
+) -> Result<SyncReport, SyncError> {
+    match &opt.record_bundle {
+        Some(bundle_path) => record_sync(bundle_path, sub_path_in, sub_path_out, BundleReference::Spans(ref_spans.clone()), ref_fps, sub_encoding, opt),
+        None => sync_spans(sub_path_in, sub_path_out, ref_spans, ref_fps, sub_encoding, opt)
+    }
+}
+
+///
+/// Synchronizes a subtitle file with the timespans of the given voice-activity (see `sync`)
+/// 
+/// Equivalent to `sync` with `TimeSpans::from(activity)`, except that the voice-activity
+/// itself is recorded if `record_bundle` is set, so that it can be cleaned or converted
+/// otherwise when investigating the sync.
+/// 
+pub fn sync_with_activity(
+    sub_path_in: &str,
+    sub_path_out: &str,
+    activity: &VoiceActivity,
+    ref_fps: f64,
+    sub_encoding: Option<String>,
+    opt: &SyncOptions
+) -> Result<SyncReport, SyncError> {
+    match &opt.record_bundle {
+        Some(bundle_path) => record_sync(bundle_path, sub_path_in, sub_path_out, BundleReference::Activity(activity.clone()), ref_fps, sub_encoding, opt),
+        None => sync_spans(sub_path_in, sub_path_out, &TimeSpans::from(&activity.runs()), ref_fps, sub_encoding, opt)
+    }
+}
+
+///
+/// Synchronizes a subtitle file with reference timespans (see `sync`) without recording
+/// 
+fn sync_spans(
+    sub_path_in: &str,
+    sub_path_out: &str,
+    ref_spans: &TimeSpans,
+    ref_fps: f64,
+    sub_encoding: Option<String>,
+    opt: &SyncOptions
+) -> Result<SyncReport, SyncError> {
+
+    let deadline = Deadline::start(opt.time_budget);
+    let mut report = SyncReport::default();
+
+    if opt.reject_unusable_reference {
+        ensure_usable_reference(ref_spans)?;
+    }
 
     let (mut sub_file_in, _) = open_sub_file(sub_path_in, sub_encoding)?;
     let sub_spans_in = TimeSpans::from_sub_file(&sub_file_in)?;
 
     let sub_spans: Vec<TimeSpan> = sub_spans_in.to_alass_timespans(opt.interval);
+    let known_spans: Option<Vec<TimeSpan>> = ref_spans.coverage().to_alass_timespans(opt.interval);
     let ref_spans: Vec<TimeSpan> = ref_spans.to_alass_timespans(opt.interval);
 
     // Framerate correction
     let (fps_factor, sub_spans): (f64, Vec<TimeSpan>) = 
         if opt.framerate_correction {
-            let (_, fr) = guess_fps_ratio(&ref_spans, &sub_spans, ref_fps);
-            if fr.ratio - 1.0 < std::f64::EPSILON {
+            let (_, fr, skipped) = guess_fps_ratio(&ref_spans, &sub_spans, ref_fps, &deadline);
+            if (fr.ratio - 1.0).abs() > f64::EPSILON {
                 info!("detected framerate = {:.3} (reference_framerate = {:.3})", fr.fps, ref_fps)
             };
+            if skipped > 0 {
+                report.degradations.push(FramerateCandidatesSkipped { skipped });
+            }
             let scaled_spans = sub_spans.into_iter().map(|x| x.scaled(fr.ratio)).collect();
             (fr.ratio, scaled_spans)
         } else {
@@ -95,15 +203,29 @@ pub fn sync(
     // Align spans with reference
     let deltas = 
         if !opt.split_mode {
-            let inc_span_cnt = sub_spans.len();
-            let (delta, _) = align_nosplit(&ref_spans, &sub_spans, standard_scoring, NoProgressInfo {});
-            info!("no split mode: shifting subtitles by {}ms", delta * opt.interval);
-            vec::from_elem(delta, inc_span_cnt)
+            align_constant(&ref_spans, &sub_spans, opt.interval)
         } else {
-            let (deltas, _) = align(&ref_spans, &sub_spans, opt.split_penalty, opt.speed_optimization, standard_scoring, NoProgressInfo {});
-            info!("split mode: shifting first subtitle by {}ms and last by {}ms", delta_str(deltas.first(), opt.interval), delta_str(deltas.last(), opt.interval));
-            deltas
+            match align_split(&ref_spans, &sub_spans, known_spans.as_deref(), opt, &deadline, &mut report) {
+                Some(deltas) => deltas,
+                None => {
+                    report.degradations.push(SplitModeDisabled);
+                    align_constant(&ref_spans, &sub_spans, opt.interval)
+                }
+            }
         };
+
+    for d in report.degradations.iter() {
+        warn!("time budget exceeded: {}", d);
+    }
+
+    // Ensure alignment is trustworthy before touching the output file
+    report.confidence = confidence(&ref_spans, &sub_spans, &deltas, known_spans.as_deref());
+    info!("alignment confidence: {:.3}", report.confidence);
+    if let Some(min_confidence) = opt.min_confidence {
+        if report.confidence < min_confidence {
+            return Err(LowConfidence { confidence: report.confidence, min_confidence, report });
+        }
+    }
     
     // Generate corrected subtitle entries
     let corrected_entries: Vec<SubtitleEntry> = sub_spans_in.into_iter()
@@ -122,7 +244,97 @@ pub fn sync(
     // Write corrected file to disk
     save_sub_file(sub_path_out, &sub_file_in)?;
 
-    Ok(())
+    Ok(report)
+}
+
+///
+/// Aligns timespans using a single constant shift
+/// 
+/// Only overlap with reference speech is rewarded, which lies within known regions of a
+/// partially covered reference. Lines shifted into unknown regions neither gain nor lose
+/// score, so a constant shift ignores unknown regions without collapsing them.
+/// 
+fn align_constant(ref_spans: &[TimeSpan], sub_spans: &[TimeSpan], interval: i64) -> Vec<TimeDelta> {
+    let (delta, _) = align_nosplit(ref_spans, sub_spans, standard_scoring, NoProgressInfo {});
+    info!("no split mode: shifting subtitles by {}ms", delta * interval);
+    vec::from_elem(delta, sub_spans.len())
+}
+
+///
+/// Aligns timespans allowing for splits. Returns `None` if the time budget is already spent.
+/// 
+/// Alignment cannot be interrupted once started, so `speed_optimization` is raised up front
+/// if more than `SPEED_BUDGET_SHARE` of the budget has been spent by the time it begins.
+/// 
+/// If `split_penalty_auto` is enabled the penalty is selected from several candidates, skipping
+/// those that would start after the budget is spent.
+/// 
+/// If the reference is partially covered, lines are aligned on a timeline of the `known`
+/// regions only (see `CollapsedSpans`), so that unknown regions are not mistaken for silence.
+/// 
+fn align_split(
+    ref_spans: &[TimeSpan],
+    sub_spans: &[TimeSpan],
+    known: Option<&[TimeSpan]>,
+    opt: &SyncOptions,
+    deadline: &Deadline,
+    report: &mut SyncReport
+) -> Option<Vec<TimeDelta>> {
+    if deadline.expired() {
+        debug!("time budget spent before split mode alignment");
+        return None
+    }
+
+    let speed = match opt.speed_optimization {
+        Some(s) if s >= RAISED_SPEED_OPTIMIZATION => opt.speed_optimization,
+        _ if deadline.spent(SPEED_BUDGET_SHARE) => {
+            report.degradations.push(SpeedOptimizationRaised { from: opt.speed_optimization, to: RAISED_SPEED_OPTIMIZATION });
+            Some(RAISED_SPEED_OPTIMIZATION)
+        },
+        _ => opt.speed_optimization
+    };
+
+    // Align against known regions only, with lines placed by a constant shift of all of them
+    let collapsed = known.map(|known| {
+        let (anchor, _) = align_nosplit(ref_spans, sub_spans, standard_scoring, NoProgressInfo {});
+        CollapsedSpans::new(known, ref_spans, sub_spans, anchor)
+    });
+    let (ref_spans, sub_spans) = match &collapsed {
+        Some(c) => (c.ref_spans.as_slice(), c.sub_spans.as_slice()),
+        None => (ref_spans, sub_spans)
+    };
+
+    let (penalty, deltas) = if opt.split_penalty_auto {
+        let (penalty, deltas, skipped) = select_split_penalty(ref_spans, sub_spans, speed, deadline);
+        if skipped > 0 {
+            report.degradations.push(SplitPenaltyCandidatesSkipped { skipped });
+        }
+        info!("split mode: selected split penalty {}", penalty);
+        (penalty, deltas)
+    } else {
+        let (deltas, _) = align(ref_spans, sub_spans, opt.split_penalty, speed, standard_scoring, NoProgressInfo {});
+        (opt.split_penalty, deltas)
+    };
+    let deltas = match &collapsed {
+        Some(c) => c.expand(&deltas),
+        None => deltas
+    };
+    info!("split mode: shifting first subtitle by {}ms and last by {}ms", delta_str(deltas.first(), opt.interval), delta_str(deltas.last(), opt.interval));
+    report.split_penalty = Some(penalty);
+    Some(deltas)
+}
+
+///
+/// Ensure that reference timespans are suitable for syncing
+/// 
+pub fn ensure_usable_reference(ref_spans: &TimeSpans) -> Result<(), SyncError> {
+    let diagnosis = ref_spans.diagnose();
+    debug!("{}", diagnosis);
+    if diagnosis.is_usable() {
+        Ok(())
+    } else {
+        Err(UnusableReference { issues: diagnosis.issues })
+    }
 }
 
 ///
@@ -174,16 +386,16 @@ pub fn open_sub_file(path: &str, sub_encoding: Option<String>) -> Result<(Subtit
 /// 
 pub fn parse_sub_file(data: &[u8], format: SubtitleFormat, sub_encoding: Option<String>) -> SubparseResult<SubtitleFile> {
     let parse_bytes = |encoding: &'static Encoding| -> SubparseResult<SubtitleFile> {
-        subparse::parse_bytes(format, &data, Some(encoding), DEFAULT_MICRODVD_FPS)
+        subparse::parse_bytes(format, data, Some(encoding), DEFAULT_MICRODVD_FPS)
     };
     match lookup_encoding(sub_encoding) {
         Some(user_encoding) => 
             parse_bytes(user_encoding).or_else(|_| {
-                let detected_encoding = detect_encoding_or(&data, UTF_8);
+                let detected_encoding = detect_encoding_or(data, UTF_8);
                 error!("Error parsing subtitles as '{}', trying '{}'...", user_encoding.name(), detected_encoding.name());
                 parse_bytes(detected_encoding)
             }),
-        None => parse_bytes(detect_encoding_or(&data, UTF_8))
+        None => parse_bytes(detect_encoding_or(data, UTF_8))
     }
 }
 
@@ -224,19 +436,33 @@ fn get_file_bytes(path: &str) -> Result<Vec<u8>, SyncError> {
 struct FramerateRatio { pub fps: f64, pub ratio: f64 }
 
 ///
-/// Aligns timespans using several candidate framerates and returns the best
+/// Aligns timespans using several candidate framerates and returns the best along with
+/// the number of candidates skipped to stay within the time budget
 /// 
-fn guess_fps_ratio(ref_spans: &[TimeSpan], inc_spans: &[TimeSpan], ref_fps: f64) -> (f64, FramerateRatio) {
-    FRAMERATES.iter()
+/// Candidates are evaluated in order of proximity to the reference framerate so the
+/// most likely candidates are checked first. Each candidate is scored by a constant shift,
+/// which ignores unknown regions of a partially covered reference (see `align_constant`).
+/// 
+fn guess_fps_ratio(ref_spans: &[TimeSpan], inc_spans: &[TimeSpan], ref_fps: f64, deadline: &Deadline) -> (f64, FramerateRatio, usize) {
+    let mut candidates: Vec<FramerateRatio> = FRAMERATES.iter()
         .map(|&fps| FramerateRatio { fps, ratio: fps / ref_fps })
+        .collect();
+    candidates.sort_by(|a, b| (a.ratio - 1.0).abs().partial_cmp(&(b.ratio - 1.0).abs()).unwrap());
+
+    let candidate_cnt = candidates.len();
+    let checked = Cell::new(0);
+    let (score, fr) = candidates.into_iter()
+        .take_while(|_| checked.get() == 0 || !deadline.spent(FRAMERATE_BUDGET_SHARE))
         .map(|fr| {
+            checked.set(checked.get() + 1);
             let candidate_spans: Vec<TimeSpan> = inc_spans.iter().map(|ts| ts.scaled(fr.ratio)).collect();
             let (delta, score) = align_nosplit(ref_spans, &candidate_spans, overlap_scoring, NoProgressHandler);
             debug!("checking framerate {:.4}fps (score: {:.4}, delta: {}ms)", fr.fps, score, delta);
             (score, fr)
         })
         .max_by_key(|(score, _)| (score * 1_000_000_000.0) as i64)
-        .unwrap()
+        .unwrap();
+    (score, fr, candidate_cnt - checked.get())
 }
 
 // Progress noop
@@ -259,6 +485,8 @@ pub enum SyncError {
     ParseError { path: String },
     WriteError { path: String, cause: io::Error },
     SerializeError { path: String, cause: subparse::errors::Error },
+    LowConfidence { confidence: f64, min_confidence: f64, report: SyncReport },
+    UnusableReference { issues: Vec<ReferenceIssue> },
     InternalError { msg: String },
 }
 
@@ -277,6 +505,11 @@ impl fmt::Display for SyncError {
             ParseError { path } => write!(f, "Error parsing subtitle file (path='{}')", path),
             WriteError { path, cause } => write!(f, "Error writing subtitle data to disk (msg='{}' path='{}')", cause, path),
             SerializeError { path, cause } => write!(f, "Error serializing subtitle data (msg='{}', path='{}')", cause.kind(), path),
+            LowConfidence { confidence, min_confidence, .. } => write!(f, "Alignment confidence too low, output not written (confidence={:.3}, min_confidence={:.3})", confidence, min_confidence),
+            UnusableReference { issues } => {
+                let issues: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+                write!(f, "Reference timespans are unusable for syncing (issues='{}')", issues.join(", "))
+            },
             InternalError { msg } => write!(f, "Unknown sync error occurred (msg='{}')", msg)
         }
     }
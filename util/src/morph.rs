@@ -4,8 +4,7 @@
 /// 
 pub fn morph_opening(input: &[bool], radius: usize) -> Vec<bool> {
     let eroded = morph_erosion(input, radius);
-    let dilated = morph_dilation(&eroded, radius);
-    dilated
+    morph_dilation(&eroded, radius)
 }
 
 ///
@@ -14,8 +13,7 @@ pub fn morph_opening(input: &[bool], radius: usize) -> Vec<bool> {
 /// 
 pub fn morph_closing(input: &[bool], radius: usize) -> Vec<bool> {
     let dilated = morph_dilation(input, radius);
-    let eroded = morph_erosion(&dilated, radius);
-    eroded
+    morph_erosion(&dilated, radius)
 }
 
 ///
@@ -23,7 +21,7 @@ pub fn morph_closing(input: &[bool], radius: usize) -> Vec<bool> {
 /// 
 pub fn morph_dilation(input: &[bool], radius: usize) -> Vec<bool> {
     let mut output = input.to_vec();
-    let (start, end) = (radius, input.len() - radius);
+    let (start, end) = (radius, input.len().saturating_sub(radius));
     for i in start .. end {
         if !input[i] {
             for j in 1 ..= radius {
@@ -42,7 +40,7 @@ pub fn morph_dilation(input: &[bool], radius: usize) -> Vec<bool> {
 /// 
 pub fn morph_erosion(input: &[bool], radius: usize) -> Vec<bool> {
     let mut output = input.to_vec();
-    let (start, end) = (radius, input.len() - radius);
+    let (start, end) = (radius, input.len().saturating_sub(radius));
     for i in start .. end {
         if input[i] {
             for j in 1 ..= radius {
@@ -55,3 +53,66 @@ pub fn morph_erosion(input: &[bool], radius: usize) -> Vec<bool> {
     }
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(pattern: &str) -> Vec<bool> {
+        pattern.chars().map(|c| c == '#').collect()
+    }
+
+    fn pattern(mask: &[bool]) -> String {
+        mask.iter().map(|s| if *s { '#' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(pattern(&morph_erosion(&[], 2)), "");
+        assert_eq!(pattern(&morph_dilation(&[], 2)), "");
+        assert_eq!(pattern(&morph_opening(&[], 2)), "");
+        assert_eq!(pattern(&morph_closing(&[], 2)), "");
+    }
+
+    #[test]
+    fn input_shorter_than_radius() {
+        let short = mask("#.#");
+        assert_eq!(pattern(&morph_erosion(&short, 5)), "#.#");
+        assert_eq!(pattern(&morph_dilation(&short, 5)), "#.#");
+        assert_eq!(pattern(&morph_opening(&short, 5)), "#.#");
+        assert_eq!(pattern(&morph_closing(&short, 5)), "#.#");
+    }
+
+    #[test]
+    fn all_speech() {
+        let speech = mask("######");
+        assert_eq!(pattern(&morph_erosion(&speech, 1)), "######");
+        assert_eq!(pattern(&morph_dilation(&speech, 1)), "######");
+        assert_eq!(pattern(&morph_opening(&speech, 1)), "######");
+        assert_eq!(pattern(&morph_closing(&speech, 1)), "######");
+    }
+
+    #[test]
+    fn all_silence() {
+        let silence = mask("......");
+        assert_eq!(pattern(&morph_erosion(&silence, 1)), "......");
+        assert_eq!(pattern(&morph_dilation(&silence, 1)), "......");
+        assert_eq!(pattern(&morph_opening(&silence, 1)), "......");
+        assert_eq!(pattern(&morph_closing(&silence, 1)), "......");
+    }
+
+    #[test]
+    fn runs_at_both_ends() {
+        let ends = mask("##....##");
+        assert_eq!(pattern(&morph_erosion(&ends, 1)), "#......#");
+        assert_eq!(pattern(&morph_dilation(&ends, 1)), "###..###");
+        assert_eq!(pattern(&morph_opening(&ends, 1)), "##....##");
+        assert_eq!(pattern(&morph_closing(&ends, 1)), "##....##");
+    }
+
+    #[test]
+    fn opening_removes_short_runs_and_closing_fills_short_gaps() {
+        assert_eq!(pattern(&morph_opening(&mask("..###.#...####.."), 1)), "..###.....####..");
+        assert_eq!(pattern(&morph_closing(&mask("..###.#...####.."), 1)), "..#####...####..");
+    }
+}
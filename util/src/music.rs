@@ -0,0 +1,113 @@
+#![allow(clippy::needless_arbitrary_self_type)]
+
+/// Duration of the window around each chunk over which its features are compared (millis)
+const WINDOW_MILLIS: u64 = 1500;
+
+/// Windows quieter than this on average are considered silence rather than music (dBFS)
+const SILENCE_DB: f32 = -50.0;
+
+/// Chunks quieter than this fraction of the window's mean energy count as low-energy chunks
+const LOW_ENERGY_RATIO: f32 = 0.5;
+
+/// Chunks crossing zero this many times more often than the window's mean count as
+/// high zero-crossing chunks
+const HIGH_ZCR_RATIO: f32 = 1.5;
+
+/// Fractions of low-energy chunks between which a window goes from music to speech. Speech
+/// pauses between syllables and words several times a second, while music rarely does.
+const LOW_ENERGY_RANGE: (f32, f32) = (0.08, 0.25);
+
+/// Fractions of high zero-crossing chunks between which a window goes from music to speech.
+/// Speech alternates between voiced and unvoiced sounds, while music is mostly tonal.
+const HIGH_ZCR_RANGE: (f32, f32) = (0.04, 0.15);
+
+/// Weight of the low-energy feature in the combined likelihood, the remainder going to the
+/// zero-crossing feature
+const LOW_ENERGY_WEIGHT: f32 = 0.7;
+
+///
+/// Lightweight music/speech discriminator run alongside the voice-activity detector
+/// 
+/// Stores the energy and zero-crossing rate of each chunk and, once all chunks are known,
+/// rates each chunk by how music-like the window centered on it is: music has a steady
+/// envelope with few low-energy chunks and little variation in zero-crossing rate, whereas
+/// speech is interrupted by short pauses and alternates between voiced and unvoiced sounds.
+/// 
+pub(crate) struct MusicClassifier {
+    half_window: usize,
+    features: Vec<Option<Features>>
+}
+
+#[derive(Clone, Copy)]
+struct Features {
+    energy: f32,
+    zcr: f32
+}
+
+impl MusicClassifier {
+    pub fn new(frame_millis: u64) -> Self {
+        MusicClassifier {
+            half_window: (WINDOW_MILLIS / frame_millis / 2) as usize,
+            features: Vec::new()
+        }
+    }
+
+    ///
+    /// Records the features of the next chunk
+    /// 
+    pub fn push(self: &mut Self, chunk: &[i16]) {
+        let len = chunk.len().max(1) as f32;
+        let energy = chunk.iter().map(|s| { let s = *s as f32 / 32768.0; s * s }).sum::<f32>() / len;
+        let crossings = chunk.windows(2).filter(|w| (w[0] < 0) != (w[1] < 0)).count();
+        self.features.push(Some(Features { energy, zcr: crossings as f32 / len }));
+    }
+
+    ///
+    /// Records a chunk whose audio is unknown
+    /// 
+    pub fn push_unknown(self: &mut Self) {
+        self.features.push(None);
+    }
+
+    pub fn reset(self: &mut Self) {
+        self.features.clear();
+    }
+
+    ///
+    /// Likelihood of music dominating each chunk recorded so far, between 0 and 1
+    /// 
+    /// Chunks are rated zero if their window is silent or mostly unknown.
+    /// 
+    pub fn likelihood(self: &Self) -> Vec<f32> {
+        let features = &self.features;
+        let mut window: Vec<Features> = Vec::with_capacity(2 * self.half_window + 1);
+        (0 .. features.len())
+            .map(|i| {
+                let start = i.saturating_sub(self.half_window);
+                let end = (i + self.half_window + 1).min(features.len());
+                window.clear();
+                window.extend(features[start .. end].iter().flatten());
+                if window.len() * 2 < end - start || window.len() < 2 {
+                    return 0.0;
+                }
+                let count = window.len() as f32;
+                let mean_energy = window.iter().map(|f| f.energy).sum::<f32>() / count;
+                if 10.0 * mean_energy.max(1e-10).log10() < SILENCE_DB {
+                    return 0.0;
+                }
+                let mean_zcr = window.iter().map(|f| f.zcr).sum::<f32>() / count;
+                let low_energy = window.iter().filter(|f| f.energy < mean_energy * LOW_ENERGY_RATIO).count() as f32 / count;
+                let high_zcr = window.iter().filter(|f| f.zcr > mean_zcr * HIGH_ZCR_RATIO).count() as f32 / count;
+                LOW_ENERGY_WEIGHT * music_score(low_energy, LOW_ENERGY_RANGE) + (1.0 - LOW_ENERGY_WEIGHT) * music_score(high_zcr, HIGH_ZCR_RANGE)
+            })
+            .collect()
+    }
+}
+
+///
+/// Maps a speech feature onto a music score: one at or below the low end of `range`, zero
+/// at or above its high end and linear in between
+/// 
+fn music_score(value: f32, range: (f32, f32)) -> f32 {
+    ((range.1 - value) / (range.1 - range.0)).clamp(0.0, 1.0)
+}
@@ -1,3 +1,7 @@
+#![allow(clippy::doc_overindented_list_items)]
+
+use std::time::Duration;
+
 ///
 /// Options governing the synchronization process
 /// 
@@ -15,16 +19,45 @@
 ///    value of 1000 means that all lines will be shifted by the same offset, while 0.01 will produce
 ///    MANY segments with different offsets. Values from 1 to 20 are the most reasonable.
 /// 
+/// * `split_penalty_auto`: When true, `split_penalty` is chosen automatically by aligning with
+///    several candidate penalties and weighing the alignment score against the number of splits.
+///    The chosen penalty is reported in `SyncReport`. Makes split-mode syncing several times slower.
+/// 
 /// * `speed_optimization`: Greatly speeds up synchronization by sacrificing accuracy.
 /// 
 /// * `framerate_correction`: Whether to attempt correction of mismatched framerates.
 /// 
+/// * `time_budget`: Maximum time to spend on a sync. The sync is gracefully degraded as the
+///    budget is spent by skipping framerate and split penalty candidates, raising
+///    `speed_optimization`, and finally falling back to a constant shift. The budget is
+///    checked between alignments, which cannot be interrupted once started, and a constant
+///    shift is always computed, so the budget may still be exceeded on very slow devices.
+///    No limit is imposed if `None`.
+/// 
+/// * `min_confidence`: Minimum alignment confidence (between 0 and 1) required to write the
+///    synced subtitles. Guards against syncing to the wrong movie or to audio without speech.
+///    See `SyncReport::confidence`. Output is always written if `None`.
+/// 
+/// * `reject_unusable_reference`: When true, syncing fails with `UnusableReference` if the
+///    reference timespans are unsuitable for alignment (see `TimeSpans::diagnose`). Disabling
+///    it lets callers sync against references they know to be sparse or unusually shaped.
+/// 
+/// * `record_bundle`: Path to which a bundle reproducing the sync is written, holding the
+///    input subtitle, reference, options and outcome (see `SyncBundle`). Lets problems
+///    reported by users be replayed with `replay_bundle`. Nothing is recorded if `None`.
+/// 
+#[derive(Clone)]
 pub struct SyncOptions {
     pub interval: i64,
     pub split_mode: bool,
     pub split_penalty: f64,
+    pub split_penalty_auto: bool,
     pub speed_optimization: Option<f64>,
-    pub framerate_correction: bool
+    pub framerate_correction: bool,
+    pub time_budget: Option<Duration>,
+    pub min_confidence: Option<f64>,
+    pub reject_unusable_reference: bool,
+    pub record_bundle: Option<String>
 }
 
 impl Default for SyncOptions {
@@ -33,8 +66,13 @@ impl Default for SyncOptions {
             interval: 60,
             split_mode: true,
             split_penalty: 7.0,
+            split_penalty_auto: false,
             speed_optimization: Some(1.0),
-            framerate_correction: false
+            framerate_correction: false,
+            time_budget: None,
+            min_confidence: None,
+            reject_unusable_reference: true,
+            record_bundle: None
         }
     }
 }
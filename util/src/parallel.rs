@@ -0,0 +1,196 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::thread;
+use std::panic;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, mpsc};
+
+use crate::{AudioSink, AudioSinkError, AudioFormat, VadConfig, VoiceActivity, ActivitySource, Sample};
+use crate::AudioSinkError::*;
+
+/// Length of audio processed by each VAD instance (millis). A whole number of seconds and of
+/// every `VadFrame` so that segments start on both a sample and a chunk boundary.
+const SEGMENT_MILLIS: u64 = 120_000;
+
+/// Audio preceding each segment that is processed to settle resampler and detector state
+/// before the segment starts, then discarded (millis). Same constraints as `SEGMENT_MILLIS`.
+const WARMUP_MILLIS: u64 = 6_000;
+
+/// Audio following each segment that is processed so that resampler latency does not cut
+/// off the last chunk of the segment, then discarded (millis)
+const LOOKAHEAD_MILLIS: u64 = 1_000;
+
+///
+/// Computes voice-activity of a whole track of decoded audio using several threads
+/// 
+/// Samples are split into segments of two minutes, each processed by an independent
+/// `AudioSink` created with `format` and `vad_config`, and the results are concatenated
+/// into a single `VoiceActivity` starting at zero.
+/// 
+/// Detectors and resamplers carry state from one chunk to the next, which independent
+/// instances cannot share. To keep segment boundaries from showing in the result, each
+/// instance also processes the six seconds preceding and the second following its segment
+/// and discards the chunks outside of it. Results therefore closely match those of a single
+/// `AudioSink` but are not identical, and depend only on the audio, not on the number of
+/// threads.
+/// 
+/// * `samples`: Interleaved samples in `format`, holding a whole number of frames.
+/// 
+/// * `threads`: Maximum number of threads to process segments on. Segments are copied out
+///    of `samples` as threads become available, so that at most twice as many segments as
+///    threads are held in memory at a time. Zero is treated as one.
+/// 
+/// Fails if any segment fails to be processed or closed, rather than stitching in partial
+/// results.
+/// 
+pub fn parallel_voice_activity<S: Sample>(format: &AudioFormat, vad_config: VadConfig, samples: &[S], threads: usize) -> Result<VoiceActivity, AudioSinkError> {
+    format.validate()?;
+    if !samples.chunks_exact(format.channels).remainder().is_empty() {
+        return Err(InvalidFormat { msg: format!("buffer length must be a multiple of the channel count (length={}, channels={})", samples.len(), format.channels) });
+    }
+    process_segments(format, vad_config, samples.len(), threads, |range| samples[range].iter().map(|s| s.to_i16()).collect())
+}
+
+///
+/// Computes voice-activity of a whole track of decoded audio given as raw little-endian
+/// bytes encoded according to `format.sample_format` (see `parallel_voice_activity`)
+/// 
+/// The buffer need not be aligned but must hold a whole number of frames.
+/// 
+pub fn parallel_voice_activity_bytes(format: &AudioFormat, vad_config: VadConfig, bytes: &[u8], threads: usize) -> Result<VoiceActivity, AudioSinkError> {
+    format.validate()?;
+    let sample_format = format.sample_format;
+    let frame_bytes = sample_format.bytes() * format.channels;
+    if !bytes.chunks_exact(frame_bytes).remainder().is_empty() {
+        return Err(InvalidFormat { msg: format!("buffer length must be a multiple of the frame size (length={}, frame_size={})", bytes.len(), frame_bytes) });
+    }
+    let sample_bytes = sample_format.bytes();
+    process_segments(format, vad_config, bytes.len() / sample_bytes, threads, |range| {
+        bytes[range.start * sample_bytes .. range.end * sample_bytes].chunks_exact(sample_bytes).map(|b| sample_format.decode(b)).collect()
+    })
+}
+
+///
+/// Splits `sample_count` interleaved samples into segments, converting the samples of each
+/// segment with `convert` as threads become available, and stitches the results together
+/// 
+fn process_segments<F>(format: &AudioFormat, vad_config: VadConfig, sample_count: usize, threads: usize, convert: F) -> Result<VoiceActivity, AudioSinkError>
+    where F: Fn(Range<usize>) -> Vec<i16> {
+    let frames = sample_count / format.channels;
+    let to_frames = |millis: u64| (millis * format.sample_rate as u64 / 1000) as usize;
+    let segment_frames = to_frames(SEGMENT_MILLIS);
+    let segment_count = (0 .. frames).step_by(segment_frames).len().max(1);
+    let segment_chunks = (SEGMENT_MILLIS / vad_config.frame.millis()) as usize;
+    let warmup_chunks = (WARMUP_MILLIS / vad_config.frame.millis()) as usize;
+
+    // Short tracks are processed in one piece on the calling thread
+    if segment_count == 1 {
+        let mut sink = AudioSink::with_config(format, vad_config)?;
+        sink.send_samples(&convert(0 .. sample_count))?;
+        sink.close()?;
+        return sink.try_voice_activity();
+    }
+
+    let (job_tx, job_rx) = mpsc::sync_channel::<Segment>(threads.max(1));
+    let (result_tx, result_rx) = mpsc::channel();
+    let job_rx = Arc::new(Mutex::new(job_rx));
+    let workers: Vec<_> = (0 .. threads.max(1).min(segment_count))
+        .map(|_| {
+            let job_rx = Arc::clone(&job_rx);
+            let result_tx = result_tx.clone();
+            let format = format.clone();
+            thread::spawn(move || loop {
+                let segment = match job_rx.lock().map(|rx| rx.recv()) {
+                    Ok(Ok(segment)) => segment,
+                    _ => break
+                };
+                let result = segment.process(&format, vad_config);
+                if result_tx.send((segment.index, result)).is_err() {
+                    break
+                }
+            })
+        })
+        .collect();
+
+    // Only workers may hold the receiver, so that queueing fails rather than blocks if all exit
+    drop(job_rx);
+    drop(result_tx);
+
+    // Queue segments in order, blocking while all workers are busy
+    for index in 0 .. segment_count {
+        let start = (index * segment_frames).saturating_sub(to_frames(WARMUP_MILLIS));
+        let end = ((index + 1) * segment_frames + to_frames(LOOKAHEAD_MILLIS)).min(frames);
+        let segment = Segment {
+            index,
+            samples: convert(start * format.channels .. end * format.channels),
+            skip_chunks: if index > 0 { warmup_chunks } else { 0 },
+            take_chunks: if index + 1 < segment_count { segment_chunks } else { usize::MAX }
+        };
+        if job_tx.send(segment).is_err() {
+            break
+        }
+    }
+    drop(job_tx);
+
+    let mut results: Vec<_> = result_rx.iter().collect();
+    for worker in workers {
+        if let Err(e) = worker.join() {
+            panic::resume_unwind(e);
+        }
+    }
+    if results.len() != segment_count {
+        return Err(VoiceDetectionError);
+    }
+
+    // Stitch segments together in order
+    results.sort_by_key(|(index, _)| *index);
+    let mut activity = VoiceActivity {
+        data: Vec::new(),
+        likelihood: Vec::new(),
+        known: Vec::new(),
+        music: Vec::new(),
+        start_millis: 0,
+        chunk_millis: vad_config.frame.millis(),
+        vad_config,
+        source: Some(ActivitySource { name: None, sample_rate: format.sample_rate, channels: format.channels })
+    };
+    for (_, result) in results {
+        let segment = result?;
+        activity.data.extend(segment.data);
+        activity.likelihood.extend(segment.likelihood);
+        activity.known.extend(segment.known);
+        activity.music.extend(segment.music);
+    }
+    Ok(activity)
+}
+
+///
+/// A segment of audio queued for processing, including warm-up and lookahead
+/// 
+/// * `skip_chunks`: Chunks of warm-up preceding the segment.
+/// * `take_chunks`: Chunks belonging to the segment, following the warm-up.
+/// 
+struct Segment {
+    index: usize,
+    samples: Vec<i16>,
+    skip_chunks: usize,
+    take_chunks: usize
+}
+
+impl Segment {
+    fn process(self: &Self, format: &AudioFormat, vad_config: VadConfig) -> Result<VoiceActivity, AudioSinkError> {
+        let mut sink = AudioSink::with_config(format, vad_config)?;
+        sink.send_samples(&self.samples)?;
+        sink.close()?;
+        let activity = sink.try_voice_activity()?;
+        let range = |v: &[bool]| v.iter().copied().skip(self.skip_chunks).take(self.take_chunks).collect();
+        let range_f32 = |v: &[f32]| v.iter().copied().skip(self.skip_chunks).take(self.take_chunks).collect();
+        Ok(VoiceActivity {
+            data: range(&activity.data),
+            likelihood: range_f32(&activity.likelihood),
+            known: range(&activity.known),
+            music: range_f32(&activity.music),
+            ..activity
+        })
+    }
+}
@@ -0,0 +1,259 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::f32::consts::PI;
+
+/// Cutoff of the high-pass filter removing DC offset (Hz)
+const DC_CUTOFF_HZ: f32 = 20.0;
+
+/// Highest band-pass cutoff relative to the sample rate, keeping the low-pass filter stable
+/// near the Nyquist frequency. Higher cutoffs disable the low-pass filter.
+const MAX_CUTOFF_RATIO: f32 = 0.45;
+
+/// Quality factors of the second-order sections of a fourth-order Butterworth filter
+const BUTTERWORTH_Q: [f32; 2] = [0.541_196, 1.306_563];
+
+/// Noise gate: rate at which the noise floor estimate rises towards louder frames (per second)
+const NOISE_FLOOR_RISE: f32 = 0.5;
+
+/// Frames quieter than this are considered silence and ignored by loudness estimation (dBFS)
+const SILENCE_DB: f32 = -60.0;
+
+/// Loudness normalization: time over which loudness is averaged (millis)
+const LOUDNESS_WINDOW_MILLIS: f32 = 3000.0;
+
+/// Loudness normalization: largest gain applied to quiet audio (dB)
+const MAX_GAIN_DB: f32 = 30.0;
+
+///
+/// Preprocessing applied to audio at the detection rate before voice-activity detection
+/// 
+/// Stages are applied in the order listed and each is disabled when `false` or `None`.
+/// All stages are disabled by default (see `Preprocessing::speech` for recommended settings).
+/// 
+/// * `dc_removal`: Removes any constant offset from the signal, which some recordings carry
+///    and which inflates frame energy.
+/// 
+/// * `band_pass`: Removes frequencies outside of the given band, such as mains hum, wind
+///    rumble and cymbals, keeping those carrying most of the energy of speech. Uses
+///    fourth-order Butterworth filters (24dB per octave).
+/// 
+/// * `noise_gate`: Silences frames whose energy does not exceed the tracked noise floor by
+///    the given margin (dB), suppressing steady background noise.
+/// 
+/// * `normalize`: Adjusts the gain so that loudness averaged over the last few seconds
+///    approaches the given target (dBFS), so that quiet and loud soundtracks are treated
+///    alike. Quiet audio is amplified by at most 30dB.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Preprocessing {
+    pub dc_removal: bool,
+    pub band_pass: Option<BandPass>,
+    pub noise_gate: Option<f32>,
+    pub normalize: Option<f32>
+}
+
+impl Preprocessing {
+
+    ///
+    /// Settings suited to film and TV soundtracks: DC removal, a 200-4000Hz band-pass, a
+    /// noise gate 6dB above the noise floor and normalization to -20dBFS
+    /// 
+    pub fn speech() -> Self {
+        Preprocessing {
+            dc_removal: true,
+            band_pass: Some(BandPass { low_hz: 200.0, high_hz: 4000.0 }),
+            noise_gate: Some(6.0),
+            normalize: Some(-20.0)
+        }
+    }
+
+    ///
+    /// Whether any stage is enabled
+    /// 
+    pub fn is_enabled(self: &Self) -> bool {
+        self.dc_removal || self.band_pass.is_some() || self.noise_gate.is_some() || self.normalize.is_some()
+    }
+}
+
+///
+/// Pass band of the band-pass filter (Hz)
+/// 
+/// The high cutoff is ignored if it lies close to or above half the detection rate, where
+/// the signal has already been band-limited by resampling.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BandPass {
+    pub low_hz: f32,
+    pub high_hz: f32
+}
+
+///
+/// Applies `Preprocessing` to successive frames of a stream
+/// 
+pub(crate) struct Preprocessor {
+    settings: Preprocessing,
+    dc_filter: Option<DcFilter>,
+    band_pass: Vec<Biquad>,
+    noise_rise: f32,
+    noise_db: Option<f32>,
+    loudness_rate: f32,
+    loudness_db: Option<f32>,
+    buffer: Vec<f32>
+}
+
+impl Preprocessor {
+    pub fn new(settings: Preprocessing, rate: u32, frame_millis: u64) -> Self {
+        let rate = rate as f32;
+        let band_pass = settings.band_pass;
+        Preprocessor {
+            settings,
+            dc_filter: if settings.dc_removal { Some(DcFilter::new(rate)) } else { None },
+            band_pass: band_pass.iter()
+                .flat_map(|b| {
+                    let high_pass = Some(b.low_hz).filter(|f| *f > 0.0 && *f < rate * MAX_CUTOFF_RATIO);
+                    let low_pass = Some(b.high_hz).filter(|f| *f > 0.0 && *f < rate * MAX_CUTOFF_RATIO);
+                    BUTTERWORTH_Q.iter().flat_map(move |q| {
+                        high_pass.map(|f| Biquad::high_pass(f, rate, *q)).into_iter()
+                            .chain(low_pass.map(|f| Biquad::low_pass(f, rate, *q)))
+                    })
+                })
+                .collect(),
+            noise_rise: NOISE_FLOOR_RISE * frame_millis as f32 / 1000.0,
+            noise_db: None,
+            loudness_rate: frame_millis as f32 / LOUDNESS_WINDOW_MILLIS,
+            loudness_db: None,
+            buffer: Vec::new()
+        }
+    }
+
+    ///
+    /// Processes a single frame, replacing `output` with the result
+    /// 
+    pub fn process(self: &mut Self, frame: &[i16], output: &mut Vec<i16>) {
+        let buffer = &mut self.buffer;
+        buffer.clear();
+        buffer.extend(frame.iter().map(|s| *s as f32));
+
+        if let Some(filter) = self.dc_filter.as_mut() {
+            buffer.iter_mut().for_each(|s| *s = filter.process(*s));
+        }
+        for filter in self.band_pass.iter_mut() {
+            buffer.iter_mut().for_each(|s| *s = filter.process(*s));
+        }
+
+        let mut energy_db = energy_db(buffer);
+
+        if let Some(margin_db) = self.settings.noise_gate {
+            // Track noise floor: follow quieter frames immediately, louder frames slowly
+            let noise_db = match self.noise_db {
+                Some(n) if energy_db < n => energy_db,
+                Some(n) => n + (energy_db - n) * self.noise_rise,
+                None => energy_db
+            };
+            self.noise_db = Some(noise_db);
+            if energy_db < noise_db + margin_db {
+                buffer.iter_mut().for_each(|s| *s = 0.0);
+                energy_db = f32::NEG_INFINITY;
+            }
+        }
+
+        if let Some(target_db) = self.settings.normalize {
+            if energy_db > SILENCE_DB {
+                self.loudness_db = Some(match self.loudness_db {
+                    Some(l) => l + (energy_db - l) * self.loudness_rate,
+                    None => energy_db
+                });
+            }
+            if let Some(loudness_db) = self.loudness_db {
+                let gain = 10f32.powf((target_db - loudness_db).min(MAX_GAIN_DB) / 20.0);
+                buffer.iter_mut().for_each(|s| *s *= gain);
+            }
+        }
+
+        output.clear();
+        output.extend(buffer.iter().map(|s| s.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16));
+    }
+
+    ///
+    /// Clears all state carried across frames
+    /// 
+    pub fn reset(self: &mut Self) {
+        if let Some(filter) = self.dc_filter.as_mut() {
+            *filter = DcFilter { r: filter.r, x1: 0.0, y1: 0.0 };
+        }
+        for filter in self.band_pass.iter_mut() {
+            filter.reset();
+        }
+        self.noise_db = None;
+        self.loudness_db = None;
+    }
+}
+
+///
+/// Energy of a frame of 16-bit samples (dBFS)
+/// 
+fn energy_db(frame: &[f32]) -> f32 {
+    let power = frame.iter().map(|s| { let s = s / 32768.0; s * s }).sum::<f32>() / frame.len().max(1) as f32;
+    10.0 * power.max(1e-10).log10()
+}
+
+///
+/// First-order high-pass filter with a very low cutoff, removing DC offset
+/// 
+struct DcFilter { r: f32, x1: f32, y1: f32 }
+
+impl DcFilter {
+    fn new(rate: f32) -> Self {
+        DcFilter { r: (-2.0 * PI * DC_CUTOFF_HZ / rate).exp(), x1: 0.0, y1: 0.0 }
+    }
+
+    fn process(self: &mut Self, x: f32) -> f32 {
+        let y = x - self.x1 + self.r * self.y1;
+        self.x1 = x;
+        self.y1 = y;
+        y
+    }
+}
+
+///
+/// Second-order filter section (see the RBJ audio EQ cookbook)
+/// 
+struct Biquad { b: [f32; 3], a: [f32; 2], x: [f32; 2], y: [f32; 2] }
+
+impl Biquad {
+    fn high_pass(cutoff: f32, rate: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::params(cutoff, rate, q);
+        Self::normalized([(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    fn low_pass(cutoff: f32, rate: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::params(cutoff, rate, q);
+        Self::normalized([(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0], [1.0 + alpha, -2.0 * cos, 1.0 - alpha])
+    }
+
+    fn params(cutoff: f32, rate: f32, q: f32) -> (f32, f32) {
+        let w = 2.0 * PI * cutoff / rate;
+        (w.cos(), w.sin() / (2.0 * q))
+    }
+
+    fn normalized(b: [f32; 3], a: [f32; 3]) -> Self {
+        Biquad {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+            x: [0.0; 2],
+            y: [0.0; 2]
+        }
+    }
+
+    fn process(self: &mut Self, x: f32) -> f32 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[0] * self.y[0] - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+
+    fn reset(self: &mut Self) {
+        self.x = [0.0; 2];
+        self.y = [0.0; 2];
+    }
+}
@@ -0,0 +1,169 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use log::*;
+
+use alass_core::*;
+
+use crate::{TimeSpans, SyncOptions, SyncError, open_sub_file, guess_fps_ratio, ensure_usable_reference};
+use crate::deadline::Deadline;
+use crate::scoring::confidence;
+use crate::coverage::CollapsedSpans;
+
+///
+/// Scores describing how well a candidate subtitle fits the reference
+/// 
+/// * `nosplit_score`: `alass` score of the best constant-shift alignment.
+/// 
+/// * `split_score`: `alass` score of the best alignment allowing for splits (see
+///    `SyncOptions::split_penalty`). Equals `nosplit_score` if `split_mode` is disabled.
+/// 
+/// * `fps_ratio`: Detected ratio between the candidate's framerate and the reference
+///    framerate. Always `1.0` unless `framerate_correction` is enabled.
+/// 
+/// * `confidence`: Normalized alignment confidence (see `SyncReport::confidence`).
+/// 
+#[derive(Debug, Clone)]
+pub struct CandidateScore {
+    pub nosplit_score: f64,
+    pub split_score: f64,
+    pub fps_ratio: f64,
+    pub confidence: f64
+}
+
+///
+/// A candidate subtitle file and its score (or the error that prevented scoring it)
+/// 
+/// `index` refers to the candidate's position in the list given to `rank_candidates`.
+/// 
+#[derive(Debug)]
+pub struct RankedCandidate {
+    pub index: usize,
+    pub path: String,
+    pub score: Result<CandidateScore, SyncError>
+}
+
+///
+/// Candidate subtitles ordered from best to worst fit (see `rank_candidates`)
+/// 
+#[derive(Debug)]
+pub struct CandidateRanking(pub Vec<RankedCandidate>);
+
+impl CandidateRanking {
+
+    ///
+    /// Returns the best fitting candidate, if any could be scored
+    /// 
+    pub fn best(self: &Self) -> Option<&RankedCandidate> {
+        self.0.first().filter(|c| c.score.is_ok())
+    }
+}
+
+///
+/// Scores each candidate subtitle file against the reference timespans and ranks them
+/// from best to worst fit without writing anything to disk
+/// 
+/// Candidates are ordered by alignment confidence. Candidates which could not be read or
+/// parsed are placed last in their original order. If the reference is unusable (see
+/// `TimeSpans::diagnose`) no candidate is scored, unless `reject_unusable_reference` is
+/// disabled. The `time_budget`, `min_confidence` and
+/// `split_penalty_auto` options are not applied.
+/// 
+/// * `sub_paths`: Paths to the candidate subtitle files.
+/// 
+/// * `ref_spans`: Reference timespans to use for alignment.
+/// 
+/// * `ref_fps`: Framerate of the reference video file (used for framerate correction).
+/// 
+/// * `sub_encoding`: The IANA charset encoding of the subtitle files, or `None` to detect
+///    the encoding of each file.
+/// 
+/// * `options`: Parameters governing alignment (see `SyncOptions`).
+/// 
+pub fn rank_candidates(
+    sub_paths: &[&str],
+    ref_spans: &TimeSpans,
+    ref_fps: f64,
+    sub_encoding: Option<String>,
+    opt: &SyncOptions
+) -> CandidateRanking {
+
+    let usable = if opt.reject_unusable_reference { ensure_usable_reference(ref_spans) } else { Ok(()) };
+    let known_spans: Option<Vec<TimeSpan>> = ref_spans.coverage().to_alass_timespans(opt.interval);
+    let ref_spans: Vec<TimeSpan> = ref_spans.to_alass_timespans(opt.interval);
+
+    let mut candidates: Vec<RankedCandidate> = sub_paths.iter()
+        .enumerate()
+        .map(|(index, &path)| {
+            let score = match &usable {
+                Ok(()) => score_candidate(path, &ref_spans, known_spans.as_deref(), ref_fps, sub_encoding.clone(), opt),
+                Err(SyncError::UnusableReference { issues }) => Err(SyncError::UnusableReference { issues: issues.clone() }),
+                Err(e) => Err(SyncError::InternalError { msg: e.to_string() })
+            };
+            match &score {
+                Ok(s) => debug!("scored candidate '{}' (confidence: {:.3}, nosplit: {:.4}, split: {:.4}, fps ratio: {:.4})",
+                    path, s.confidence, s.nosplit_score, s.split_score, s.fps_ratio),
+                Err(e) => warn!("unable to score candidate '{}': {}", path, e)
+            }
+            RankedCandidate { index, path: path.to_string(), score }
+        })
+        .collect();
+
+    // Best confidence first, unscored candidates last (stable sort preserves input order)
+    candidates.sort_by(|a, b| match (&a.score, &b.score) {
+        (Ok(a), Ok(b)) => b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => std::cmp::Ordering::Equal
+    });
+
+    CandidateRanking(candidates)
+}
+
+///
+/// Aligns a single candidate subtitle file with the reference and computes its scores
+/// 
+/// Only known regions are considered if the reference is partially covered (see
+/// `CollapsedSpans`).
+/// 
+fn score_candidate(
+    sub_path: &str,
+    ref_spans: &[TimeSpan],
+    known_spans: Option<&[TimeSpan]>,
+    ref_fps: f64,
+    sub_encoding: Option<String>,
+    opt: &SyncOptions
+) -> Result<CandidateScore, SyncError> {
+
+    let (sub_file, _) = open_sub_file(sub_path, sub_encoding)?;
+    let sub_spans = TimeSpans::from_sub_file(&sub_file)?.to_alass_timespans(opt.interval);
+
+    let fps_ratio = if opt.framerate_correction {
+        let (_, fr, _) = guess_fps_ratio(ref_spans, &sub_spans, ref_fps, &Deadline::start(None));
+        fr.ratio
+    } else {
+        1.0
+    };
+    let sub_spans: Vec<TimeSpan> = sub_spans.into_iter().map(|s| s.scaled(fps_ratio)).collect();
+
+    let (delta, nosplit_score) = align_nosplit(ref_spans, &sub_spans, standard_scoring, NoProgressHandler);
+
+    let (deltas, split_score) = if opt.split_mode {
+        match known_spans {
+            Some(known) => {
+                let collapsed = CollapsedSpans::new(known, ref_spans, &sub_spans, delta);
+                let (deltas, score) = align(&collapsed.ref_spans, &collapsed.sub_spans, opt.split_penalty, opt.speed_optimization, standard_scoring, NoProgressHandler);
+                (collapsed.expand(&deltas), score)
+            },
+            None => align(ref_spans, &sub_spans, opt.split_penalty, opt.speed_optimization, standard_scoring, NoProgressHandler)
+        }
+    } else {
+        (vec![delta; sub_spans.len()], nosplit_score)
+    };
+
+    Ok(CandidateScore {
+        nosplit_score,
+        split_score,
+        fps_ratio,
+        confidence: confidence(ref_spans, &sub_spans, &deltas, known_spans)
+    })
+}
@@ -0,0 +1,67 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::fmt;
+
+///
+/// Summary of a completed synchronization
+/// 
+/// * `degradations`: Measures taken to complete the sync within the configured
+///    `time_budget` (see `SyncOptions`). Empty if the sync ran as configured.
+/// 
+/// * `split_penalty`: The split penalty used for alignment, which may have been selected
+///    automatically (see `SyncOptions::split_penalty_auto`). `None` if subtitles were
+///    shifted by a constant offset.
+/// 
+/// * `confidence`: Normalized confidence in the alignment ranging from zero (no better than
+///    a random shift) to one (perfect match with the reference).
+/// 
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub degradations: Vec<Degradation>,
+    pub split_penalty: Option<f64>,
+    pub confidence: f64
+}
+
+impl SyncReport {
+
+    ///
+    /// Whether any measures were taken to satisfy the time budget
+    /// 
+    pub fn is_degraded(self: &Self) -> bool {
+        !self.degradations.is_empty()
+    }
+}
+
+///
+/// A measure taken to keep a sync within its time budget
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub enum Degradation {
+    /// Some candidate framerates were not evaluated during framerate correction
+    FramerateCandidatesSkipped { skipped: usize },
+
+    /// Some candidate split penalties were not evaluated during automatic selection
+    SplitPenaltyCandidatesSkipped { skipped: usize },
+
+    /// Split-mode alignment was run with a higher `speed_optimization` value
+    SpeedOptimizationRaised { from: Option<f64>, to: f64 },
+
+    /// Split-mode alignment was skipped in favor of a constant shift
+    SplitModeDisabled
+}
+
+impl fmt::Display for Degradation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Degradation::FramerateCandidatesSkipped { skipped } =>
+                write!(f, "skipped {} framerate candidate(s)", skipped),
+            Degradation::SplitPenaltyCandidatesSkipped { skipped } =>
+                write!(f, "skipped {} split penalty candidate(s)", skipped),
+            Degradation::SpeedOptimizationRaised { from, to } => {
+                let from = from.map(|v| format!("{}", v)).unwrap_or_else(|| String::from("none"));
+                write!(f, "raised speed_optimization from {} to {}", from, to)
+            },
+            Degradation::SplitModeDisabled => write!(f, "disabled split mode")
+        }
+    }
+}
@@ -0,0 +1,134 @@
+#![allow(clippy::needless_arbitrary_self_type)]
+
+use std::f64::consts::PI;
+
+/// Number of zero-crossings of the sinc kernel on either side of its center. Higher values
+/// give a sharper anti-aliasing filter at the expense of speed.
+const ZERO_CROSSINGS: usize = 16;
+
+/// Number of fractional positions for which the kernel is precomputed. Positions in between
+/// are linearly interpolated.
+const PHASES: usize = 512;
+
+/// Fraction of the output Nyquist frequency kept by the anti-aliasing filter
+const ROLLOFF: f64 = 0.94;
+
+///
+/// Streaming band-limited resampler for mono 16-bit audio
+/// 
+/// Uses a Blackman-windowed sinc kernel whose cutoff follows the lower of the two Nyquist
+/// frequencies, making it suitable for both decimation (e.g. 48kHz to 8kHz) and
+/// interpolation. Input may be fed in arbitrarily sized pieces; the output is identical to
+/// that of resampling the whole stream at once.
+/// 
+pub struct Resampler {
+    in_rate: u64,
+    out_rate: u64,
+    half_width: usize,
+    kernel: Vec<f32>,
+    buffer: Vec<f32>,
+    index: usize,
+    remainder: u64
+}
+
+impl Resampler {
+
+    ///
+    /// Creates a resampler converting from `in_rate` to `out_rate` (Hz)
+    /// 
+    pub fn new(in_rate: u32, out_rate: u32) -> Self {
+        let cutoff = (out_rate as f64 / in_rate as f64).min(1.0) * ROLLOFF;
+        let half_width = (ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+        let taps = 2 * half_width;
+
+        // Kernel rows for each phase (plus one to simplify interpolation), each holding the
+        // weights for input samples at offsets `-half_width + 1 ..= half_width`
+        let mut kernel = vec![0f32; (PHASES + 1) * taps];
+        for phase in 0 ..= PHASES {
+            let frac = phase as f64 / PHASES as f64;
+            for tap in 0 .. taps {
+                let x = (tap as f64 - half_width as f64 + 1.0) - frac;
+                kernel[phase * taps + tap] = (cutoff * sinc(cutoff * x) * blackman(x / half_width as f64)) as f32;
+            }
+        }
+
+        Resampler {
+            in_rate: in_rate as u64,
+            out_rate: out_rate as u64,
+            half_width,
+            kernel,
+            buffer: vec![0f32; half_width],
+            index: half_width,
+            remainder: 0
+        }
+    }
+
+    ///
+    /// Resamples the given input, appending any samples that can be produced to `output`
+    /// 
+    pub fn process(self: &mut Self, input: &[i16], output: &mut Vec<i16>) {
+        self.buffer.extend(input.iter().map(|&s| s as f32));
+        self.drain(output);
+    }
+
+    ///
+    /// Flushes samples held back awaiting future input. No further input should be
+    /// processed once flushed.
+    /// 
+    pub fn flush(self: &mut Self, output: &mut Vec<i16>) {
+        self.buffer.resize(self.buffer.len() + self.half_width, 0f32);
+        self.drain(output);
+    }
+
+    ///
+    /// Flushes held back samples and restarts the stream, as if newly created. Used to
+    /// resume after a discontinuity in the input.
+    /// 
+    pub fn reset(self: &mut Self, output: &mut Vec<i16>) {
+        self.flush(output);
+        self.buffer.clear();
+        self.buffer.resize(self.half_width, 0f32);
+        self.index = self.half_width;
+        self.remainder = 0;
+    }
+
+    fn drain(self: &mut Self, output: &mut Vec<i16>) {
+        let taps = 2 * self.half_width;
+        while self.index + self.half_width < self.buffer.len() {
+            // Position of the next output sample is `index + remainder / out_rate` (exact)
+            let frac = (self.remainder as f64 / self.out_rate as f64) * PHASES as f64;
+            let phase = frac.floor() as usize;
+            let blend = (frac - phase as f64) as f32;
+
+            let window = &self.buffer[self.index + 1 - self.half_width ..= self.index + self.half_width];
+            let k0 = &self.kernel[phase * taps .. (phase + 1) * taps];
+            let k1 = &self.kernel[(phase + 1) * taps .. (phase + 2) * taps];
+            let sample: f32 = window.iter().zip(k0.iter().zip(k1.iter()))
+                .map(|(s, (a, b))| s * (a + (b - a) * blend))
+                .sum();
+            output.push(sample.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+
+            self.remainder += self.in_rate;
+            self.index += (self.remainder / self.out_rate) as usize;
+            self.remainder %= self.out_rate;
+        }
+
+        // Discard input which is no longer needed
+        let consumed = (self.index + 1).saturating_sub(self.half_width).min(self.buffer.len());
+        self.buffer.drain(.. consumed);
+        self.index -= consumed;
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-9 { 1.0 } else { (PI * x).sin() / (PI * x) }
+}
+
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        0.0
+    } else {
+        let t = PI * (x + 1.0);
+        0.42 - 0.5 * t.cos() + 0.08 * (2.0 * t).cos()
+    }
+}
@@ -0,0 +1,269 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::ops::Range;
+
+use subparse::timetypes::{TimeSpan as SubTimeSpan, TimePoint as SubTimePoint};
+
+use crate::{VoiceActivity, TimeSpans, Coverage};
+
+///
+/// Run-length encoded voice-activity
+/// 
+/// Holds ranges of chunk indices rather than a flag per chunk, so that its size and the cost
+/// of morphological operations grow with the number of runs instead of the duration of the
+/// audio. Chunks outside of `0 .. len` are considered non-speech.
+/// 
+/// * `speech`: Runs of consecutive speech chunks, in order, neither empty, overlapping nor
+///    adjacent.
+/// 
+/// * `known`: Runs of consecutive known chunks (see `VoiceActivity::known`), likewise.
+/// 
+/// * `len`: Total number of chunks.
+/// 
+/// * `start_millis`: Timestamp of the first chunk (see `VoiceActivity::start_millis`).
+/// 
+/// * `chunk_millis`: Duration of each chunk.
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityRuns {
+    pub speech: Vec<Range<usize>>,
+    pub known: Vec<Range<usize>>,
+    pub len: usize,
+    pub start_millis: i64,
+    pub chunk_millis: u64
+}
+
+impl ActivityRuns {
+
+    ///
+    /// Number of speech chunks
+    /// 
+    pub fn speech_chunks(self: &Self) -> usize {
+        self.speech.iter().map(|r| r.len()).sum()
+    }
+
+    ///
+    /// Expands to a flag per chunk, as in `VoiceActivity::data`
+    /// 
+    pub fn speech_mask(self: &Self) -> Vec<bool> {
+        let mut mask = vec![false; self.len];
+        for run in self.speech.iter() {
+            mask[run.clone()].iter_mut().for_each(|s| *s = true);
+        }
+        mask
+    }
+
+    ///
+    /// The morphological 'dilate' operator, growing each run by `radius` chunks on either side
+    /// and merging runs that meet
+    /// 
+    pub fn dilate(self: &Self, radius: usize) -> Self {
+        self.extend(radius, radius)
+    }
+
+    ///
+    /// The morphological 'erode' operator, shrinking each run by `radius` chunks on either
+    /// side and dropping runs no longer than `2 * radius` chunks
+    /// 
+    pub fn erode(self: &Self, radius: usize) -> Self {
+        let speech = self.speech.iter()
+            .map(|run| run.start.saturating_add(radius) .. run.end.saturating_sub(radius))
+            .filter(|run| run.start < run.end)
+            .collect();
+        ActivityRuns { speech, ..self.clone() }
+    }
+
+    ///
+    /// The morphological 'opening' operator, removing runs of at most `2 * radius` chunks
+    /// 
+    pub fn opening(self: &Self, radius: usize) -> Self {
+        self.erode(radius).dilate(radius)
+    }
+
+    ///
+    /// The morphological 'closing' operator, filling gaps of at most `2 * radius` chunks.
+    /// Runs within `radius` chunks of either end are cut back, as chunks outside of the
+    /// activity are non-speech.
+    /// 
+    pub fn closing(self: &Self, radius: usize) -> Self {
+        self.dilate(radius).erode(radius)
+    }
+
+    ///
+    /// Joins runs separated by gaps of at most `max_gap` chunks. Unlike `closing`, runs near
+    /// either end are left as they are.
+    /// 
+    pub fn bridge_gaps(self: &Self, max_gap: usize) -> Self {
+        let mut speech: Vec<Range<usize>> = Vec::with_capacity(self.speech.len());
+        for run in self.speech.iter() {
+            match speech.last_mut() {
+                Some(last) if run.start - last.end <= max_gap => last.end = run.end,
+                _ => speech.push(run.clone())
+            }
+        }
+        ActivityRuns { speech, ..self.clone() }
+    }
+
+    ///
+    /// Removes runs shorter than `min_len` chunks. Unlike `opening`, runs of exactly
+    /// `min_len` chunks are kept, so that any length may be required.
+    /// 
+    pub fn drop_shorter(self: &Self, min_len: usize) -> Self {
+        let speech = self.speech.iter().filter(|run| run.len() >= min_len).cloned().collect();
+        ActivityRuns { speech, ..self.clone() }
+    }
+
+    ///
+    /// Extends each run by `before` chunks before its start and `after` chunks past its end,
+    /// merging runs that meet
+    /// 
+    pub fn extend(self: &Self, before: usize, after: usize) -> Self {
+        let mut speech: Vec<Range<usize>> = Vec::with_capacity(self.speech.len());
+        for run in self.speech.iter() {
+            let grown = run.start.saturating_sub(before) .. run.end.saturating_add(after).min(self.len);
+            match speech.last_mut() {
+                Some(last) if grown.start <= last.end => last.end = last.end.max(grown.end),
+                _ => speech.push(grown)
+            }
+        }
+        ActivityRuns { speech, ..self.clone() }
+    }
+}
+
+impl From<&VoiceActivity> for ActivityRuns {
+    fn from(activity: &VoiceActivity) -> ActivityRuns {
+        ActivityRuns {
+            speech: mask_runs(&activity.data),
+            known: mask_runs(&activity.known),
+            len: activity.data.len(),
+            start_millis: activity.start_millis,
+            chunk_millis: activity.chunk_millis
+        }
+    }
+}
+
+///
+/// Produces `TimeSpans` directly from runs, without expanding them
+/// 
+/// Coverage is `Partial` unless a single known run spans all chunks (see `Coverage`).
+/// 
+impl From<&ActivityRuns> for TimeSpans {
+    fn from(runs: &ActivityRuns) -> TimeSpans {
+        let span = |run: &Range<usize>| SubTimeSpan::new(
+            SubTimePoint::from_msecs(runs.start_millis + run.start as i64 * runs.chunk_millis as i64),
+            SubTimePoint::from_msecs(runs.start_millis + run.end as i64 * runs.chunk_millis as i64));
+        let fully_known = runs.len == 0 || runs.known.iter().map(|r| r.len()).sum::<usize>() == runs.len;
+        let coverage = if fully_known { Coverage::Full } else { Coverage::Partial(runs.known.iter().map(span).collect()) };
+        TimeSpans::with_coverage(runs.speech.iter().map(span).collect(), coverage)
+    }
+}
+
+///
+/// Runs of consecutive set flags
+/// 
+fn mask_runs(mask: &[bool]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut run_start = None;
+    for (i, set) in mask.iter().enumerate() {
+        match (run_start, *set) {
+            (None, true) => run_start = Some(i),
+            (Some(start), false) => {
+                runs.push(start .. i);
+                run_start = None;
+            },
+            _ => ()
+        }
+    }
+    if let Some(start) = run_start {
+        runs.push(start .. mask.len());
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(pattern: &str) -> Vec<bool> {
+        pattern.chars().map(|c| c == '#').collect()
+    }
+
+    fn runs(pattern: &str) -> ActivityRuns {
+        let speech = mask(pattern);
+        ActivityRuns {
+            speech: mask_runs(&speech),
+            known: mask_runs(&vec![true; speech.len()]),
+            len: speech.len(),
+            start_millis: 0,
+            chunk_millis: 10
+        }
+    }
+
+    fn pattern(runs: &ActivityRuns) -> String {
+        runs.speech_mask().iter().map(|s| if *s { '#' } else { '.' }).collect()
+    }
+
+    #[test]
+    fn empty_input() {
+        let empty = runs("");
+        assert_eq!(pattern(&empty.erode(2)), "");
+        assert_eq!(pattern(&empty.dilate(2)), "");
+        assert_eq!(pattern(&empty.opening(2)), "");
+        assert_eq!(pattern(&empty.closing(2)), "");
+    }
+
+    #[test]
+    fn input_shorter_than_radius() {
+        let short = runs("#.#");
+        assert_eq!(pattern(&short.erode(5)), "...");
+        assert_eq!(pattern(&short.dilate(5)), "###");
+        assert_eq!(pattern(&short.opening(5)), "...");
+        assert_eq!(pattern(&short.closing(5)), "...");
+    }
+
+    #[test]
+    fn all_speech() {
+        let speech = runs("######");
+        assert_eq!(pattern(&speech.erode(1)), ".####.");
+        assert_eq!(pattern(&speech.dilate(1)), "######");
+        assert_eq!(pattern(&speech.opening(1)), "######");
+        assert_eq!(pattern(&speech.closing(1)), ".####.");
+    }
+
+    #[test]
+    fn all_silence() {
+        let silence = runs("......");
+        assert_eq!(pattern(&silence.erode(1)), "......");
+        assert_eq!(pattern(&silence.dilate(1)), "......");
+        assert_eq!(pattern(&silence.opening(1)), "......");
+        assert_eq!(pattern(&silence.closing(1)), "......");
+    }
+
+    #[test]
+    fn runs_at_both_ends() {
+        let ends = runs("##....##");
+        assert_eq!(pattern(&ends.erode(1)), "........");
+        assert_eq!(pattern(&ends.dilate(1)), "###..###");
+        assert_eq!(pattern(&ends.opening(1)), "........");
+        assert_eq!(pattern(&ends.closing(1)), ".#....#.");
+    }
+
+    #[test]
+    fn opening_removes_short_runs_and_closing_fills_short_gaps() {
+        assert_eq!(pattern(&runs("..###.#...####..").opening(1)), "..###.....####..");
+        assert_eq!(pattern(&runs("..###.#...####..").closing(1)), "..#####...####..");
+    }
+
+    #[test]
+    fn timespans_from_runs() {
+        let mut activity = runs("##....##");
+        activity.start_millis = 100;
+        let spans = TimeSpans::from(&activity);
+        let span = |start, end| SubTimeSpan::new(SubTimePoint::from_msecs(start), SubTimePoint::from_msecs(end));
+        assert_eq!(spans.spans(), &[span(100, 120), span(160, 180)]);
+        assert_eq!(spans.coverage(), &Coverage::Full);
+
+        activity.known = mask_runs(&mask("..####.."));
+        assert_eq!(TimeSpans::from(&activity).coverage(), &Coverage::Partial(vec![span(120, 160)]));
+    }
+}
@@ -0,0 +1,134 @@
+use std::cell::Cell;
+
+use log::debug;
+
+use alass_core::*;
+
+use crate::deadline::Deadline;
+use crate::coverage::collapse_unknown;
+
+/// Number of circular shifts used to estimate the score of a chance alignment
+const CHANCE_SHIFTS: i64 = 16;
+
+/// Candidate split penalties evaluated when selecting a penalty automatically. Ordered by
+/// preference so the most promising candidates are evaluated first under a time budget.
+pub const AUTO_SPLIT_PENALTIES: &[f64] = &[ 7.0, 4.0, 12.0, 2.0, 20.0, 1.0 ];
+
+///
+/// Computes how well the subtitle spans fit the reference once shifted by the given deltas
+/// 
+/// Each perfectly matching pair of spans contributes a score of 1, so the result is roughly
+/// the number of well aligned subtitle lines.
+/// 
+pub fn fit_score(ref_spans: &[TimeSpan], sub_spans: &[TimeSpan], deltas: &[TimeDelta]) -> f64 {
+    let mut ref_sorted = ref_spans.to_vec();
+    ref_sorted.sort_by(|a, b| a.cmp_start(*b));
+
+    let mut shifted: Vec<TimeSpan> = sub_spans.iter().zip(deltas.iter())
+        .map(|(&span, &delta)| span + delta)
+        .collect();
+    shifted.sort_by(|a, b| a.cmp_start(*b));
+
+    get_nosplit_score(ref_sorted.into_iter(), shifted.into_iter(), standard_scoring)
+}
+
+///
+/// Counts the number of splits (changes of offset between consecutive lines)
+/// 
+pub fn split_count(deltas: &[TimeDelta]) -> usize {
+    deltas.windows(2).filter(|w| w[0] != w[1]).count()
+}
+
+///
+/// Computes a normalized confidence in `[0, 1]` for an alignment
+/// 
+/// The fit score of the alignment is compared with the score expected by chance, estimated
+/// by circularly shifting the aligned subtitle spans across the reference, and with the best
+/// possible score, that of the subtitle spans aligned with themselves. A confidence near
+/// zero indicates the alignment is no better than random (e.g. the wrong movie, or audio
+/// without speech), while one indicates a perfect match.
+/// 
+/// If `known` regions are given (see `Coverage`), only those parts of the reference and of the
+/// aligned spans lying within them are compared, so that unknown regions count neither
+/// against the alignment nor towards the score expected by chance.
+/// 
+pub fn confidence(ref_spans: &[TimeSpan], sub_spans: &[TimeSpan], deltas: &[TimeDelta], known: Option<&[TimeSpan]>) -> f64 {
+    let aligned: Vec<TimeSpan> = sub_spans.iter().zip(deltas.iter())
+        .map(|(&span, &delta)| span + delta)
+        .collect();
+    let (ref_spans, aligned) = match known {
+        Some(known) => (collapse_unknown(known, ref_spans), collapse_unknown(known, &aligned)),
+        None => (ref_spans.to_vec(), aligned)
+    };
+    let ref_spans = ref_spans.as_slice();
+
+    let (first, last) = match (ref_spans.iter().map(|s| s.start).min(), ref_spans.iter().map(|s| s.end).max()) {
+        (Some(first), Some(last)) if first < last => (first.as_i64(), last.as_i64()),
+        _ => return 0.0
+    };
+    let period = last - first;
+
+    let no_deltas = vec![TimeDelta::zero(); aligned.len()];
+
+    // Circularly shift aligned spans within the reference period, dropping spans that wrap
+    let chance = (1..=CHANCE_SHIFTS)
+        .map(|k| {
+            let shift = k * period / (CHANCE_SHIFTS + 1);
+            let shifted: Vec<TimeSpan> = aligned.iter()
+                .map(|s| {
+                    let start = (s.start.as_i64() - first + shift).rem_euclid(period) + first;
+                    (start, start + s.len().as_i64())
+                })
+                .filter(|&(_, end)| end <= last)
+                .map(|(start, end)| TimeSpan::new(TimePoint::from(start), TimePoint::from(end)))
+                .collect();
+            fit_score(ref_spans, &shifted, &vec![TimeDelta::zero(); shifted.len()])
+        })
+        .sum::<f64>() / CHANCE_SHIFTS as f64;
+
+    let best = fit_score(&aligned, &aligned, &no_deltas);
+    let actual = fit_score(ref_spans, &aligned, &no_deltas);
+
+    if best - chance > 0.0 {
+        ((actual - chance) / (best - chance)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    }
+}
+
+///
+/// Selects a split penalty by aligning with each of `AUTO_SPLIT_PENALTIES` and comparing
+/// the results using a BIC-style criterion: the fit score less `ln(n)/2` for each split,
+/// where `n` is the number of subtitle lines. Ties favor fewer splits.
+/// 
+/// The first candidate is always evaluated, while the remaining ones are skipped once the
+/// time budget is spent. Returns the chosen penalty and its deltas along with the number of
+/// candidates skipped.
+/// 
+pub fn select_split_penalty(
+    ref_spans: &[TimeSpan],
+    sub_spans: &[TimeSpan],
+    speed_optimization: Option<f64>,
+    deadline: &Deadline
+) -> (f64, Vec<TimeDelta>, usize) {
+    let split_cost = (sub_spans.len().max(1) as f64).ln() / 2.0;
+
+    let checked = Cell::new(0);
+    let (_, _, penalty, deltas) = AUTO_SPLIT_PENALTIES.iter()
+        .take_while(|_| checked.get() == 0 || !deadline.expired())
+        .map(|&penalty| {
+            checked.set(checked.get() + 1);
+            let (deltas, _) = align(ref_spans, sub_spans, penalty, speed_optimization, standard_scoring, NoProgressHandler);
+            let fit = fit_score(ref_spans, sub_spans, &deltas);
+            let splits = split_count(&deltas);
+            let criterion = fit - split_cost * splits as f64;
+            debug!("checking split penalty {} (fit: {:.4}, splits: {}, criterion: {:.4})", penalty, fit, splits, criterion);
+            (criterion, splits, penalty, deltas)
+        })
+        .fold(None, |best: Option<(f64, usize, f64, Vec<TimeDelta>)>, c| match best {
+            Some(b) if b.0 > c.0 || (b.0 == c.0 && b.1 <= c.1) => Some(b),
+            _ => Some(c)
+        })
+        .unwrap();
+    (penalty, deltas, AUTO_SPLIT_PENALTIES.len() - checked.get())
+}
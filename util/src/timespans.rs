@@ -1,3 +1,5 @@
+#![allow(clippy::needless_arbitrary_self_type)]
+
 use std::fmt;
 use std::fs::File;
 use std::path::Path;
@@ -6,7 +8,6 @@ use std::io::prelude::*;
 use std::io::{BufReader, BufWriter, Cursor};
 use std::io::ErrorKind::NotFound;
 use std::error::Error;
-use std::iter::once;
 use std::convert::{TryFrom, TryInto};
 use std::cmp::{min, max};
 
@@ -17,7 +18,7 @@ use subparse::timetypes::{TimeSpan as SubTimeSpan, TimePoint as SubTimePoint};
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
-use crate::{SyncError, VoiceActivity};
+use crate::{SyncError, VoiceActivity, ActivityRuns, Coverage};
 use crate::util::to_alass_timepoint;
 
 use TimeSpansLoadError::*;
@@ -32,10 +33,41 @@ use TimeSpansSaveError::*;
 /// from video file). `TimeSpans` also provides functionality for reading and
 /// writing raw timespan data to/from disk which is useful for caching.
 /// 
-pub struct TimeSpans(pub Vec<SubTimeSpan>);
+/// Timespans also describe which regions of the timeline are known (see `Coverage`).
+/// Alignment ignores unknown regions of partially covered references.
+/// 
+#[derive(Clone)]
+pub struct TimeSpans(Vec<SubTimeSpan>, Coverage);
 
 impl TimeSpans {
 
+    ///
+    /// Creates `TimeSpans` from the given spans, with the whole timeline known
+    /// 
+    pub fn new(spans: Vec<SubTimeSpan>) -> Self {
+        TimeSpans(spans, Coverage::Full)
+    }
+
+    ///
+    /// Creates `TimeSpans` from the given spans, with only the regions given by `coverage` known
+    /// 
+    pub fn with_coverage(spans: Vec<SubTimeSpan>, coverage: Coverage) -> Self {
+        TimeSpans(spans, coverage)
+    }
+
+    ///
+    /// The timespans
+    /// 
+    pub fn spans(self: &Self) -> &[SubTimeSpan] {
+        &self.0
+    }
+
+    ///
+    /// Regions of the timeline which are known (see `Coverage`)
+    /// 
+    pub fn coverage(self: &Self) -> &Coverage {
+        &self.1
+    }
 
     ///
     /// Appends a timespan
@@ -44,6 +76,13 @@ impl TimeSpans {
         self.0.push(span)
     }
 
+    ///
+    /// Marks a region of the timeline as known (see `Coverage::push`)
+    /// 
+    pub fn push_known(self: &mut Self, region: SubTimeSpan) {
+        self.1.push(region)
+    }
+
     ///
     /// Saves raw timespan data to disk
     /// 
@@ -81,13 +120,21 @@ impl TimeSpans {
     /// 
     pub fn from_sub_file(sub_file: &SubtitleFile) -> Result<Self, SyncError> {
         let entries = sub_file.get_subtitle_entries().expect("Unable to read subtitle entries");
-        let spans = TimeSpans(entries.into_iter()
+        let spans = TimeSpans::new(entries.into_iter()
             .map(|subentry| subentry.timespan)
             .map(|span: SubTimeSpan| SubTimeSpan::new(min(span.start, span.end), max(span.start, span.end)))
             .collect());
         Ok(spans)
     }
 
+    ///
+    /// Produces `TimeSpans` from the speech likelihood of voice-activity data using threshold
+    /// hysteresis (see `VoiceActivity::hysteresis`)
+    /// 
+    pub fn from_likelihood(activity: &VoiceActivity, on_threshold: f32, off_threshold: f32) -> Self {
+        TimeSpans::from(&activity.hysteresis(on_threshold, off_threshold))
+    }
+
     ///
     /// Convert `TimeSpan`s from `subparse` to `alass_core` representation
     /// 
@@ -117,6 +164,8 @@ impl IntoIterator for TimeSpans {
 ///
 /// Serializes `TimeSpans` instance to raw bytes
 /// 
+/// Known regions of partially covered timespans follow the spans in the same layout.
+/// 
 impl<'a> TryInto<Vec<u8>> for &'a TimeSpans {
     type Error = io::Error;
 
@@ -127,6 +176,13 @@ impl<'a> TryInto<Vec<u8>> for &'a TimeSpans {
             bytes.write_i64::<LittleEndian>(s.start.msecs())?;
             bytes.write_i64::<LittleEndian>(s.end.msecs())?;
         }
+        if let Coverage::Partial(regions) = &self.1 {
+            bytes.write_u32::<LittleEndian>(regions.len() as u32)?;
+            for r in regions.iter() {
+                bytes.write_i64::<LittleEndian>(r.start.msecs())?;
+                bytes.write_i64::<LittleEndian>(r.end.msecs())?;
+            }
+        }
         Ok(bytes)
     }
 }
@@ -134,41 +190,46 @@ impl<'a> TryInto<Vec<u8>> for &'a TimeSpans {
 ///
 /// Deserializes `TimeSpans` instance from raw bytes
 /// 
+/// Data without known regions (including that written before coverage was introduced) is
+/// considered fully covered.
+/// 
 impl TryFrom<&[u8]> for TimeSpans {
     type Error = io::Error;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        fn read_spans(rdr: &mut Cursor<&[u8]>, count: u32) -> Result<Vec<SubTimeSpan>, io::Error> {
+            (0..count).map(|_| {
+                let start = rdr.read_i64::<LittleEndian>()?;
+                let end = rdr.read_i64::<LittleEndian>()?;
+                Ok(SubTimeSpan {
+                    start: SubTimePoint::from_msecs(start),
+                    end: SubTimePoint::from_msecs(end)
+                })
+            }).collect()
+        }
+
         let mut rdr = Cursor::new(bytes);
         let count = rdr.read_u32::<LittleEndian>()?;
-        let spans: Result<Vec<SubTimeSpan>, io::Error> = (0..count).map(|_| {
-            let start = rdr.read_i64::<LittleEndian>()?;
-            let end = rdr.read_i64::<LittleEndian>()?;
-            Ok(SubTimeSpan {
-                start: SubTimePoint::from_msecs(start),
-                end: SubTimePoint::from_msecs(end)
-            })
-        }).collect();
-        Ok(TimeSpans(spans?))
+        let spans = read_spans(&mut rdr, count)?;
+        let coverage = if (rdr.position() as usize) < bytes.len() {
+            let count = rdr.read_u32::<LittleEndian>()?;
+            Coverage::Partial(read_spans(&mut rdr, count)?)
+        } else {
+            Coverage::Full
+        };
+        Ok(TimeSpans(spans, coverage))
     }
 }
 
 ///
 /// Analyze vector of voice-activity data and produce `TimeSpans`
 /// 
+/// Coverage is `Partial` if any chunks are unknown (see `VoiceActivity::known`). Spans are
+/// produced from the runs of speech chunks (see `ActivityRuns`).
+/// 
 impl From<&VoiceActivity> for TimeSpans {
     fn from(activity: &VoiceActivity) -> TimeSpans {
-        let timespans: Vec<SubTimeSpan> =
-            once(&false).chain(activity.data.iter().chain(once(&false)))
-                .collect::<Vec<&bool>>()
-                .windows(2)
-                .enumerate()
-                .filter_map(|(i, v)| if *v[0] != *v[1] { Some(i as i64) } else { None })
-                .map(|t| SubTimePoint::from_msecs(t * activity.chunk_millis as i64))
-                .collect::<Vec<SubTimePoint>>()
-                .chunks(2)
-                .map(|s| SubTimeSpan::new(s[0], s[1]))
-                .collect();
-        TimeSpans(timespans)
+        TimeSpans::from(&ActivityRuns::from(activity))
     }
 }
 
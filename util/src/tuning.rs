@@ -0,0 +1,179 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::fmt;
+
+use crate::{VoiceActivity, TimeSpans, Coverage, Cleanup};
+
+/// Minimum number of subtitle lines for their statistics to be meaningful
+const MIN_SUB_SPANS: usize = 10;
+
+/// Quantiles of span and gap lengths compared between reference and subtitle
+const QUANTILES: [f64; 3] = [0.25, 0.5, 0.75];
+
+/// Speech-likelihood thresholds tried as `(on_threshold, off_threshold)` (see
+/// `VoiceActivity::hysteresis`). Only the first is tried for binary likelihoods.
+const THRESHOLDS: [(f32, f32); 4] = [(0.5, 0.5), (0.6, 0.4), (0.7, 0.5), (0.4, 0.3)];
+
+/// Candidate cleanup durations (millis, see `Cleanup`). Candidates shorter than a chunk are
+/// skipped.
+const MAX_GAP_MILLIS: [u64; 6] = [0, 100, 200, 350, 500, 800];
+const MIN_SPEECH_MILLIS: [u64; 5] = [0, 100, 200, 350, 500];
+const ONSET_MILLIS: [u64; 3] = [0, 100, 200];
+const OFFSET_MILLIS: [u64; 4] = [0, 150, 300, 500];
+
+///
+/// Cleanup and threshold parameters chosen by `tune_cleanup`
+/// 
+/// * `cleanup`: Cleanup applied after thresholding (see `VoiceActivity::clean_ms`).
+/// 
+/// * `on_threshold`, `off_threshold`: Speech-likelihood thresholds (see
+///    `VoiceActivity::hysteresis`).
+/// 
+/// * `distance`: Dissimilarity between the span statistics of the tuned reference and
+///    those of the subtitle. Zero if identical.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CleanupTuning {
+    pub cleanup: Cleanup,
+    pub on_threshold: f32,
+    pub off_threshold: f32,
+    pub distance: f64
+}
+
+impl fmt::Display for CleanupTuning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CleanupTuning(on_threshold={}, off_threshold={}, max_gap={}ms, min_speech={}ms, onset={}ms, offset={}ms, distance={:.3})",
+            self.on_threshold, self.off_threshold, self.cleanup.max_gap_millis, self.cleanup.min_speech_millis,
+            self.cleanup.onset_millis, self.cleanup.offset_millis, self.distance)
+    }
+}
+
+///
+/// Tunes the conversion of voice-activity to reference timespans against the subtitle to
+/// be synced
+/// 
+/// Whatever their timing, subtitle lines reveal how long utterances and the pauses between
+/// them typically are in the program. Thresholds and cleanup parameters are searched for
+/// the reference whose distribution of span lengths, gap lengths and coverage best matches
+/// that of `sub_spans`. Returns the tuned reference along with the chosen parameters, or
+/// `None` if the subtitle has too few lines (fewer than 10) or pauses to tell, or if no
+/// candidate leaves at least two speech spans.
+/// 
+/// Only statistics are compared, not positions, so the subtitle may be arbitrarily out of
+/// sync with the audio.
+/// 
+pub fn tune_cleanup(activity: &VoiceActivity, sub_spans: &TimeSpans) -> Option<(TimeSpans, CleanupTuning)> {
+    if sub_spans.spans().len() < MIN_SUB_SPANS {
+        return None;
+    }
+    let target = SpanStatistics::of(sub_spans)?;
+
+    let binary = activity.likelihood.iter().all(|l| *l == 0.0 || *l == 1.0);
+    let thresholds = if binary { &THRESHOLDS[.. 1] } else { &THRESHOLDS[..] };
+
+    let mut best: Option<(TimeSpans, CleanupTuning)> = None;
+    for (on_threshold, off_threshold) in thresholds.iter().copied() {
+        let runs = activity.hysteresis(on_threshold, off_threshold).runs();
+        for max_gap_millis in MAX_GAP_MILLIS.iter().copied() {
+            for min_speech_millis in MIN_SPEECH_MILLIS.iter().copied() {
+                for onset_millis in ONSET_MILLIS.iter().copied() {
+                    for offset_millis in OFFSET_MILLIS.iter().copied() {
+                        let cleanup = Cleanup { max_gap_millis, min_speech_millis, onset_millis, offset_millis };
+                        let spans = match cleanup.apply(&runs) {
+                            Ok(cleaned) => TimeSpans::from(&cleaned),
+                            Err(_) => continue
+                        };
+                        let distance = match SpanStatistics::of(&spans) {
+                            Some(stats) => stats.distance(&target),
+                            None => continue
+                        };
+                        if best.as_ref().map(|(_, b)| distance < b.distance).unwrap_or(true) {
+                            best = Some((spans, CleanupTuning { cleanup, on_threshold, off_threshold, distance }));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+///
+/// Distribution of span and gap lengths of a set of timespans
+/// 
+/// * `span_quantiles`, `gap_quantiles`: Natural logarithm of the `QUANTILES` of span and gap
+///    lengths (millis). Gaps are only taken within known regions.
+/// 
+/// * `coverage_ratio`: Fraction of the duration covered by spans.
+/// 
+struct SpanStatistics {
+    span_quantiles: Vec<f64>,
+    gap_quantiles: Vec<f64>,
+    coverage_ratio: f64
+}
+
+impl SpanStatistics {
+
+    ///
+    /// Statistics of the given timespans, or `None` if there are too few spans or gaps
+    /// 
+    fn of(spans: &TimeSpans) -> Option<Self> {
+        let mut sorted: Vec<(i64, i64)> = spans.spans().iter().map(|s| (s.start.msecs(), s.end.msecs())).filter(|(s, e)| s < e).collect();
+        sorted.sort_unstable();
+
+        // Merge overlapping spans (e.g. simultaneous subtitle lines)
+        let mut merged: Vec<(i64, i64)> = Vec::with_capacity(sorted.len());
+        for (start, end) in sorted {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end))
+            }
+        }
+
+        let known: Option<Vec<(i64, i64)>> = match spans.coverage() {
+            Coverage::Full => None,
+            Coverage::Partial(regions) => Some(regions.iter().map(|r| (r.start.msecs(), r.end.msecs())).collect())
+        };
+        let within_known = |start: i64, end: i64| known.as_ref()
+            .map(|regions| regions.iter().any(|(s, e)| *s <= start && end <= *e))
+            .unwrap_or(true);
+
+        let span_lengths: Vec<f64> = merged.iter().map(|(s, e)| (e - s) as f64).collect();
+        let gap_lengths: Vec<f64> = merged.windows(2)
+            .filter(|w| within_known(w[0].1, w[1].0))
+            .map(|w| (w[1].0 - w[0].1) as f64)
+            .collect();
+        if span_lengths.len() < 2 || gap_lengths.is_empty() {
+            return None;
+        }
+
+        let duration = match &known {
+            Some(regions) => regions.iter().map(|(s, e)| (e - s).max(0)).sum::<i64>(),
+            None => merged[merged.len() - 1].1 - merged[0].0
+        };
+        let coverage_ratio = span_lengths.iter().sum::<f64>() / duration.max(1) as f64;
+        Some(SpanStatistics {
+            span_quantiles: log_quantiles(span_lengths),
+            gap_quantiles: log_quantiles(gap_lengths),
+            coverage_ratio
+        })
+    }
+
+    ///
+    /// Sum of the differences between log-quantiles and between log-coverage ratios, so that
+    /// statistics off by the same factor count the same whatever their magnitude
+    /// 
+    fn distance(self: &Self, other: &Self) -> f64 {
+        let quantile_distance = |a: &[f64], b: &[f64]| a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum::<f64>();
+        quantile_distance(&self.span_quantiles, &other.span_quantiles)
+            + quantile_distance(&self.gap_quantiles, &other.gap_quantiles)
+            + (self.coverage_ratio.max(1e-6).ln() - other.coverage_ratio.max(1e-6).ln()).abs()
+    }
+}
+
+fn log_quantiles(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    QUANTILES.iter()
+        .map(|q| values[((values.len() - 1) as f64 * q).round() as usize].max(1.0).ln())
+        .collect()
+}
@@ -52,7 +52,7 @@ pub fn to_subparse_deltas(v: &[TimeDelta], interval: i64) -> Vec<SubTimeDelta> {
 /// Retrieves `Encoding` given it's label
 /// 
 pub fn lookup_encoding(label: Option<String>) -> Option<&'static Encoding> {
-    label.map(|l| Encoding::for_label(l.as_bytes())).flatten()
+    label.and_then(|l| Encoding::for_label(l.as_bytes()))
 }
 
 ///
@@ -0,0 +1,166 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
+
+use std::convert::TryFrom;
+
+use webrtc_vad::{SampleRate, VadMode as WebRtcVadMode};
+
+use crate::{AudioSinkError, VadBackend, Preprocessing};
+use crate::AudioSinkError::*;
+
+///
+/// Settings of the voice-activity detector and the processing of audio ahead of it
+/// 
+/// * `backend`: Voice-activity detector implementation (see `VadBackend`).
+/// 
+/// * `mode`: Sensitivity of the detector. Less aggressive modes detect more speech at the
+///    risk of false positives on music and noise. Quiet or sparsely scored films benefit
+///    from `Quality`, dialogue-heavy programs with background music from `Aggressive`. For
+///    the `Energy` backend the mode sets the margin by which speech must exceed the noise
+///    floor.
+/// 
+/// * `frame`: Duration of each chunk of audio classified by the detector. Shorter frames
+///    yield finer-grained voice-activity at the expense of speed.
+/// 
+/// * `rate`: Sample rate at which voice-activity is detected. Higher rates take more of
+///    the speech spectrum into account at the expense of speed.
+/// 
+/// * `soft`: When true, WebRTC voice-activity is graded into a likelihood by classifying
+///    each frame at every `VadMode` (see `WebRtcDetector`). About four times slower. Other
+///    backends always grade their likelihood.
+/// 
+/// * `preprocessing`: Filtering, gating and normalization applied before detection to
+///    reduce false positives on hum, wind and music (see `Preprocessing`). Disabled by
+///    default.
+/// 
+/// * `music_discrimination`: When true, the likelihood of music dominating each chunk is
+///    computed alongside voice-activity (see `VoiceActivity::music`). Disabled by default.
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VadConfig {
+    pub backend: VadBackend,
+    pub mode: VadMode,
+    pub frame: VadFrame,
+    pub rate: VadRate,
+    pub soft: bool,
+    pub preprocessing: Preprocessing,
+    pub music_discrimination: bool
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        VadConfig {
+            backend: VadBackend::WebRtc,
+            mode: VadMode::LowBitrate,
+            frame: VadFrame::Frame30ms,
+            rate: VadRate::Rate8kHz,
+            soft: false,
+            preprocessing: Preprocessing::default(),
+            music_discrimination: false
+        }
+    }
+}
+
+impl VadConfig {
+
+    ///
+    /// Number of samples in each frame at the detection rate
+    /// 
+    pub fn frame_samples(self: &Self) -> usize {
+        self.frame.millis() as usize * self.rate.hz() as usize / 1000
+    }
+}
+
+///
+/// Sensitivity of the WebRTC voice-activity detector, from least to most aggressive
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VadMode { Quality, LowBitrate, Aggressive, VeryAggressive }
+
+impl VadMode {
+
+    /// All modes, from least to most aggressive
+    pub const ALL: [VadMode; 4] = [VadMode::Quality, VadMode::LowBitrate, VadMode::Aggressive, VadMode::VeryAggressive];
+
+    pub(crate) fn webrtc(self: &Self) -> WebRtcVadMode {
+        match self {
+            VadMode::Quality => WebRtcVadMode::Quality,
+            VadMode::LowBitrate => WebRtcVadMode::LowBitrate,
+            VadMode::Aggressive => WebRtcVadMode::Aggressive,
+            VadMode::VeryAggressive => WebRtcVadMode::VeryAggressive
+        }
+    }
+}
+
+///
+/// Frame lengths supported by the voice-activity detector
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VadFrame { Frame10ms, Frame20ms, Frame30ms }
+
+impl VadFrame {
+
+    ///
+    /// The frame length in milliseconds
+    /// 
+    pub fn millis(self: &Self) -> u64 {
+        match self {
+            VadFrame::Frame10ms => 10,
+            VadFrame::Frame20ms => 20,
+            VadFrame::Frame30ms => 30
+        }
+    }
+}
+
+impl TryFrom<u32> for VadFrame {
+    type Error = AudioSinkError;
+
+    fn try_from(millis: u32) -> Result<Self, Self::Error> {
+        match millis {
+            10 => Ok(VadFrame::Frame10ms),
+            20 => Ok(VadFrame::Frame20ms),
+            30 => Ok(VadFrame::Frame30ms),
+            _ => Err(InvalidFormat { msg: format!("unsupported VAD frame length (millis={})", millis) })
+        }
+    }
+}
+
+///
+/// Sample rates supported by the voice-activity detector
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VadRate { Rate8kHz, Rate16kHz, Rate32kHz }
+
+impl VadRate {
+
+    ///
+    /// The sample rate in Hz
+    /// 
+    pub fn hz(self: &Self) -> u32 {
+        match self {
+            VadRate::Rate8kHz => 8_000,
+            VadRate::Rate16kHz => 16_000,
+            VadRate::Rate32kHz => 32_000
+        }
+    }
+
+    pub(crate) fn webrtc(self: &Self) -> SampleRate {
+        match self {
+            VadRate::Rate8kHz => SampleRate::Rate8kHz,
+            VadRate::Rate16kHz => SampleRate::Rate16kHz,
+            VadRate::Rate32kHz => SampleRate::Rate32kHz
+        }
+    }
+}
+
+impl TryFrom<u32> for VadRate {
+    type Error = AudioSinkError;
+
+    fn try_from(hz: u32) -> Result<Self, Self::Error> {
+        match hz {
+            8_000 => Ok(VadRate::Rate8kHz),
+            16_000 => Ok(VadRate::Rate16kHz),
+            32_000 => Ok(VadRate::Rate32kHz),
+            _ => Err(UnsupportedSampleRate { rate: hz })
+        }
+    }
+}
@@ -1,16 +1,116 @@
+#![allow(clippy::needless_arbitrary_self_type, clippy::doc_overindented_list_items)]
 
-use crate::morph::{morph_opening, morph_closing};
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::io;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter, Cursor};
+use std::io::ErrorKind::{NotFound, InvalidData};
+use std::error::Error;
+use std::convert::{TryFrom, TryInto};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::{ActivityRuns, Cleanup, CleanupError, SPEECH_LIKELIHOOD};
+use crate::{VadConfig, VadBackend, VadMode, VadFrame, VadRate, Preprocessing, BandPass};
+
+use VoiceActivityLoadError::*;
+use VoiceActivitySaveError::*;
+
+/// Leading bytes identifying saved voice-activity
+const MAGIC: &[u8; 4] = b"AVAD";
+
+/// Version of the layout of saved voice-activity, increased whenever it changes. Newer
+/// versions are refused when loading.
+const VERSION: u16 = 1;
 
 ///
 /// Voice activity as computed by `webrtc-vad`
 /// 
+/// * `data`: Whether speech was detected in each chunk.
+/// 
+/// * `likelihood`: Likelihood of speech in each chunk, between 0 and 1. Chunks at or above
+///    `SPEECH_LIKELIHOOD` make up `data` as detected. Use `hysteresis` to derive `data`
+///    with other thresholds without processing the audio again. `clean` and `clean_ms`
+///    reset the likelihood of chunks they flip to 1 or 0, so that it never contradicts `data`
+///    beyond the thresholds used to derive it.
+/// 
+/// * `known`: Whether the audio of each chunk was processed. Chunks filling gaps in the
+///    audio are unknown (see `AudioSink::set_timestamp`), as is everything outside of
+///    `data` when any chunk is unknown (see `Coverage`).
+/// 
+/// * `music`: Likelihood of music dominating each chunk, between 0 and 1. Songs and scores
+///    are often detected as speech but rarely carry subtitles, or carry lyrics that do not
+///    follow the detected speech. Use `without_music` or `weight_music` to exclude or
+///    down-weight them before converting to `TimeSpans`. Empty unless enabled with
+///    `VadConfig::music_discrimination`.
+/// 
+/// * `start_millis`: Timestamp of the first chunk, non-zero when audio starts at an offset
+///    from the video (see `AudioSink::set_timestamp`).
+/// 
+/// * `chunk_millis`: Duration of each chunk, equal to `vad_config.frame`.
+/// 
+/// * `vad_config`: Settings of the detector that produced `data`.
+/// 
+/// * `source`: Description of the audio `data` was detected in, if known.
+/// 
+/// Voice-activity may be saved to disk and loaded again (see `save`), so that it can be
+/// cleaned and converted with other settings without decoding the audio again.
+/// 
+#[derive(Clone)]
 pub struct VoiceActivity {
     pub data: Vec<bool>,
-    pub chunk_millis: u64
+    pub likelihood: Vec<f32>,
+    pub known: Vec<bool>,
+    pub music: Vec<f32>,
+    pub start_millis: i64,
+    pub chunk_millis: u64,
+    pub vad_config: VadConfig,
+    pub source: Option<ActivitySource>
 }
 
 impl VoiceActivity {
 
+    ///
+    /// Saves voice-activity to disk along with its settings and source (see `load`)
+    /// 
+    /// Speech and known flags are packed eight to a byte, and speech likelihood is only
+    /// stored when it holds more than `data`. Nothing is lost in the process.
+    /// 
+    pub fn save(self: &Self, filename: &str) -> Result<(), VoiceActivitySaveError> {
+        let file = File::create(Path::new(filename))
+            .map_err(|cause| WriteError { cause })?;
+        let bytes: Vec<u8> = self.try_into()
+            .map_err(|cause| SerializeError { cause })?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(bytes.as_slice())
+            .and_then(|_| writer.flush())
+            .map_err(|cause| WriteError { cause })?;
+        Ok(())
+    }
+
+    ///
+    /// Loads voice-activity saved to disk (see `save`)
+    /// 
+    /// Fails with `DeserializeError` if the file does not hold voice-activity or was saved
+    /// by a newer version of the library.
+    /// 
+    pub fn load(filename: &str) -> Result<Self, VoiceActivityLoadError> {
+        let file = File::open(Path::new(filename))
+            .map_err(|cause| match cause.kind() {
+                NotFound => FileNotFound { path: filename.to_string() },
+                _ => ReadError { cause, path: filename.to_string() }
+            })?;
+        let bytes = &mut vec![];
+        BufReader::new(file)
+            .read_to_end(bytes)
+            .map_err(|cause| ReadError { cause, path: filename.to_string() })?;
+        let activity = Self::try_from(bytes.as_slice())
+            .map_err(|cause| DeserializeError { cause })?;
+        Ok(activity)
+    }
+
     ///
     /// Cleans voice-activity data (EXPERIMENTAL)
     /// 
@@ -21,45 +121,448 @@ impl VoiceActivity {
     /// 
     /// The `opening_radius` and `closing_radius` parameters represent the kernel radii
     /// of the mathematical morphological operators. Each radius determines a window
-    /// of size `(2r+1)*CHUNK_MILLIS` milliseconds. Any errant spans smaller than this
-    /// window will be removed and any gaps larger than this window will be filled.
+    /// of size `(2r+1)*chunk_millis` milliseconds. Any errant spans smaller than this
+    /// window will be removed and any gaps smaller than this window will be filled.
+    /// Takes time proportional to the number of spans (see `ActivityRuns`).
     /// 
     pub fn clean(self: &Self, opening_radius: usize, closing_radius: usize) -> Self {
-    
-        // Clone voice-activity buffer and add padding
-        fn pad(data: &[bool], radius: usize) -> Vec<bool> {
-            let orig_len = data.len();
-            let clone_len = orig_len + radius * 2;
-            let mut clone = vec![false; clone_len];
-            clone.truncate(radius);
-            clone.extend_from_slice(&data);
-            clone.resize(clone_len, false);
-            clone
-        }
-    
-        // Remove padding
-        fn unpad(data: &[bool], radius: usize) -> Vec<bool> {
-            data[radius .. data.len()-radius].to_vec()
-        }
-
-        let data = self.data.to_owned();
-
-        // Perform morphological 'closing' operation to fill gaps
-        let data = if opening_radius > 0 {
-            let opening_clone = pad(&data, opening_radius);
-            unpad(&morph_opening(&opening_clone, opening_radius), opening_radius)
+
+        // Perform morphological 'opening' operation to remove noise, then 'closing' to fill gaps
+        self.with_runs(&self.runs().opening(opening_radius).closing(closing_radius))
+    }
+
+    ///
+    /// Cleans voice-activity data with settings expressed in milliseconds (see `Cleanup`)
+    /// 
+    /// Unlike `clean`, the result does not depend on the chunk length beyond rounding. The
+    /// result is a clone of the original `VoiceActivity` instance, or an error if any enabled
+    /// duration is shorter than `chunk_millis`.
+    /// 
+    pub fn clean_ms(self: &Self, cleanup: &Cleanup) -> Result<Self, CleanupError> {
+        Ok(self.with_runs(&cleanup.apply(&self.runs())?))
+    }
+
+    ///
+    /// Clone of this instance with speech flags taken from cleaned `runs`, and the likelihood
+    /// of chunks whose flag changed reset to 1 or 0
+    /// 
+    fn with_runs(self: &Self, runs: &ActivityRuns) -> Self {
+        let data = runs.speech_mask();
+        let likelihood = self.flipped_likelihood(&data);
+        self.with_data(data, likelihood)
+    }
+
+    ///
+    /// Clone of this instance with the given speech flags and likelihood
+    /// 
+    fn with_data(self: &Self, data: Vec<bool>, likelihood: Vec<f32>) -> Self {
+        VoiceActivity { data, likelihood, known: self.known.clone(), music: self.music.clone(), source: self.source.clone(), ..*self }
+    }
+
+    ///
+    /// Speech likelihood with chunks whose speech flag differs from `data` reset to 1 or 0
+    /// 
+    fn flipped_likelihood(self: &Self, data: &[bool]) -> Vec<f32> {
+        self.likelihood.iter().zip(self.data.iter()).zip(data.iter())
+            .map(|((l, before), after)| match (before, after) {
+                (false, true) => 1.0,
+                (true, false) => 0.0,
+                _ => *l
+            })
+            .collect()
+    }
+
+    ///
+    /// Run-length encoded speech and known chunks (see `ActivityRuns`)
+    /// 
+    pub fn runs(self: &Self) -> ActivityRuns {
+        ActivityRuns::from(self)
+    }
+
+    ///
+    /// Derives voice-activity from speech likelihood using threshold hysteresis
+    /// 
+    /// Speech starts once the likelihood reaches `on_threshold` and continues until it drops
+    /// below `off_threshold`, so that brief dips within an utterance do not split it. The
+    /// result is a clone of the original `VoiceActivity` instance with recomputed `data` and
+    /// unchanged `likelihood`, so that hysteresis may be applied again with other thresholds.
+    /// 
+    /// Thresholds lie between 0 and 1. `off_threshold` is capped at `on_threshold`; equal
+    /// thresholds amount to a plain threshold.
+    /// 
+    pub fn hysteresis(self: &Self, on_threshold: f32, off_threshold: f32) -> Self {
+        let off_threshold = off_threshold.min(on_threshold);
+        let mut speech = false;
+        let data = self.likelihood.iter()
+            .map(|l| {
+                speech = if speech { *l >= off_threshold } else { *l >= on_threshold };
+                speech
+            })
+            .collect();
+        self.with_data(data, self.likelihood.clone())
+    }
+
+    ///
+    /// Excludes music-dominated chunks from speech
+    /// 
+    /// Chunks whose music likelihood reaches `threshold` are marked as non-speech with zero
+    /// speech likelihood. The result is a clone of the original `VoiceActivity` instance. No
+    /// chunk is treated as music unless `VadConfig::music_discrimination` was enabled.
+    /// 
+    pub fn without_music(self: &Self, threshold: f32) -> Self {
+        let is_music = |i: usize| self.music.get(i).map(|m| *m >= threshold).unwrap_or(false);
+        let data = self.data.iter().enumerate().map(|(i, d)| *d && !is_music(i)).collect();
+        let likelihood = self.likelihood.iter().enumerate().map(|(i, l)| if is_music(i) { 0.0 } else { *l }).collect();
+        self.with_data(data, likelihood)
+    }
+
+    ///
+    /// Down-weights speech in proportion to music likelihood
+    /// 
+    /// Speech likelihood of each chunk is scaled by `1 - weight * music` and `data` is
+    /// recomputed from it at `SPEECH_LIKELIHOOD`, so that speech over faint music survives
+    /// while songs are dropped. `weight` lies between 0 (no effect) and 1. The result is a
+    /// clone of the original `VoiceActivity` instance. No chunk is treated as music unless
+    /// `VadConfig::music_discrimination` was enabled.
+    /// 
+    pub fn weight_music(self: &Self, weight: f32) -> Self {
+        let weight = weight.clamp(0.0, 1.0);
+        let likelihood: Vec<f32> = self.likelihood.iter().enumerate()
+            .map(|(i, l)| l * (1.0 - weight * self.music.get(i).copied().unwrap_or(0.0)))
+            .collect();
+        let data = likelihood.iter().map(|l| *l >= SPEECH_LIKELIHOOD).collect();
+        self.with_data(data, likelihood)
+    }
+}
+
+///
+/// Describes the audio voice-activity was detected in
+/// 
+/// * `name`: Name of the media, such as its path, if given.
+/// 
+/// * `sample_rate`: Sample rate of the audio as received, before resampling. (Hz)
+/// 
+/// * `channels`: Number of channels of the audio as received, before mixing down.
+/// 
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivitySource {
+    pub name: Option<String>,
+    pub sample_rate: u32,
+    pub channels: usize
+}
+
+///
+/// Serializes `VoiceActivity` instance to raw bytes
+/// 
+/// Following the magic bytes and version come the timing, detector settings and source,
+/// then each of `data`, `known`, `likelihood` and `music` preceded by its length. Flags are
+/// packed eight to a byte. Likelihood is omitted when it is zero or one as given by `data`.
+/// 
+impl<'a> TryInto<Vec<u8>> for &'a VoiceActivity {
+    type Error = io::Error;
+
+    fn try_into(self: &'a VoiceActivity) -> Result<Vec<u8>, Self::Error> {
+        fn write_bits(bytes: &mut Vec<u8>, bits: &[bool]) -> Result<(), io::Error> {
+            bytes.write_u64::<LittleEndian>(bits.len() as u64)?;
+            bytes.extend(bits.chunks(8).map(|c| c.iter().enumerate().fold(0u8, |byte, (i, b)| byte | ((*b as u8) << i))));
+            Ok(())
+        }
+        fn write_f32s(bytes: &mut Vec<u8>, values: &[f32]) -> Result<(), io::Error> {
+            bytes.write_u64::<LittleEndian>(values.len() as u64)?;
+            values.iter().try_for_each(|v| bytes.write_f32::<LittleEndian>(*v))
+        }
+        fn write_option(bytes: &mut Vec<u8>, value: Option<f32>) -> Result<(), io::Error> {
+            bytes.write_u8(value.is_some() as u8)?;
+            bytes.write_f32::<LittleEndian>(value.unwrap_or(0.0))
+        }
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.write_u16::<LittleEndian>(VERSION)?;
+        bytes.write_i64::<LittleEndian>(self.start_millis)?;
+        bytes.write_u64::<LittleEndian>(self.chunk_millis)?;
+
+        let config = &self.vad_config;
+        bytes.write_u8(match config.backend { VadBackend::WebRtc => 0, VadBackend::Energy => 1 })?;
+        bytes.write_u8(VadMode::ALL.iter().position(|m| *m == config.mode).unwrap_or(0) as u8)?;
+        bytes.write_u32::<LittleEndian>(config.frame.millis() as u32)?;
+        bytes.write_u32::<LittleEndian>(config.rate.hz())?;
+        bytes.write_u8(config.soft as u8)?;
+        bytes.write_u8(config.music_discrimination as u8)?;
+        let preprocessing = &config.preprocessing;
+        bytes.write_u8(preprocessing.dc_removal as u8)?;
+        write_option(&mut bytes, preprocessing.band_pass.map(|b| b.low_hz))?;
+        bytes.write_f32::<LittleEndian>(preprocessing.band_pass.map(|b| b.high_hz).unwrap_or(0.0))?;
+        write_option(&mut bytes, preprocessing.noise_gate)?;
+        write_option(&mut bytes, preprocessing.normalize)?;
+
+        bytes.write_u8(self.source.is_some() as u8)?;
+        if let Some(source) = &self.source {
+            bytes.write_u32::<LittleEndian>(source.sample_rate)?;
+            bytes.write_u32::<LittleEndian>(source.channels as u32)?;
+            let name = source.name.as_deref().unwrap_or("");
+            bytes.write_u8(source.name.is_some() as u8)?;
+            bytes.write_u32::<LittleEndian>(name.len() as u32)?;
+            bytes.extend(name.as_bytes());
+        }
+
+        write_bits(&mut bytes, &self.data)?;
+        write_bits(&mut bytes, &self.known)?;
+        let likelihood_given_by_data = self.likelihood.len() == self.data.len()
+            && self.likelihood.iter().zip(self.data.iter()).all(|(l, d)| *l == if *d { 1.0 } else { 0.0 });
+        bytes.write_u8(!likelihood_given_by_data as u8)?;
+        if !likelihood_given_by_data {
+            write_f32s(&mut bytes, &self.likelihood)?;
+        }
+        write_f32s(&mut bytes, &self.music)?;
+        Ok(bytes)
+    }
+}
+
+///
+/// Deserializes `VoiceActivity` instance from raw bytes, failing with `InvalidData` if they
+/// do not hold consistent voice-activity or come from a newer version
+/// 
+/// Voice-activity is consistent if chunks have a positive duration and `known` and
+/// `likelihood` hold a value per chunk, as does `music` unless it is empty.
+/// 
+impl TryFrom<&[u8]> for VoiceActivity {
+    type Error = io::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        fn invalid(msg: String) -> io::Error {
+            io::Error::new(InvalidData, msg)
+        }
+        fn read_len(rdr: &mut Cursor<&[u8]>, item_bits: u64) -> Result<usize, io::Error> {
+            let len = rdr.read_u64::<LittleEndian>()?;
+            let remaining = (rdr.get_ref().len() as u64).saturating_sub(rdr.position());
+            match len.checked_mul(item_bits) {
+                Some(bits) if bits / 8 + (bits % 8 != 0) as u64 <= remaining => Ok(len as usize),
+                _ => Err(invalid(format!("truncated voice-activity data (len={}, remaining_bytes={})", len, remaining)))
+            }
+        }
+        fn read_bits(rdr: &mut Cursor<&[u8]>) -> Result<Vec<bool>, io::Error> {
+            let len = read_len(rdr, 1)?;
+            let mut packed = vec![0u8; len / 8 + (len % 8 != 0) as usize];
+            rdr.read_exact(&mut packed)?;
+            Ok((0 .. len).map(|i| packed[i / 8] & (1 << (i % 8)) != 0).collect())
+        }
+        fn read_f32s(rdr: &mut Cursor<&[u8]>) -> Result<Vec<f32>, io::Error> {
+            let len = read_len(rdr, 32)?;
+            (0 .. len).map(|_| rdr.read_f32::<LittleEndian>()).collect()
+        }
+        fn read_option(rdr: &mut Cursor<&[u8]>) -> Result<Option<f32>, io::Error> {
+            let is_some = rdr.read_u8()? != 0;
+            let value = rdr.read_f32::<LittleEndian>()?;
+            Ok(if is_some { Some(value) } else { None })
+        }
+
+        let mut rdr = Cursor::new(bytes);
+        let mut magic = [0u8; 4];
+        rdr.read_exact(&mut magic).map_err(|_| invalid("not voice-activity data".to_string()))?;
+        if &magic != MAGIC {
+            return Err(invalid("not voice-activity data".to_string()));
+        }
+        let version = rdr.read_u16::<LittleEndian>()?;
+        if version > VERSION {
+            return Err(invalid(format!("unsupported voice-activity version (version={}, supported={})", version, VERSION)));
+        }
+        let start_millis = rdr.read_i64::<LittleEndian>()?;
+        let chunk_millis = rdr.read_u64::<LittleEndian>()?;
+        if chunk_millis == 0 {
+            return Err(invalid("chunk duration must be positive".to_string()));
+        }
+
+        let backend = match rdr.read_u8()? {
+            0 => VadBackend::WebRtc,
+            1 => VadBackend::Energy,
+            code => return Err(invalid(format!("unknown VAD backend (code={})", code)))
+        };
+        let mode = rdr.read_u8()?;
+        let mode = *VadMode::ALL.get(mode as usize)
+            .ok_or_else(|| invalid(format!("unknown VAD mode (code={})", mode)))?;
+        let frame = VadFrame::try_from(rdr.read_u32::<LittleEndian>()?).map_err(|e| invalid(e.to_string()))?;
+        let rate = VadRate::try_from(rdr.read_u32::<LittleEndian>()?).map_err(|e| invalid(e.to_string()))?;
+        let soft = rdr.read_u8()? != 0;
+        let music_discrimination = rdr.read_u8()? != 0;
+        let dc_removal = rdr.read_u8()? != 0;
+        let band_pass_low = read_option(&mut rdr)?;
+        let band_pass_high = rdr.read_f32::<LittleEndian>()?;
+        let band_pass = band_pass_low.map(|low_hz| BandPass { low_hz, high_hz: band_pass_high });
+        let noise_gate = read_option(&mut rdr)?;
+        let normalize = read_option(&mut rdr)?;
+        let preprocessing = Preprocessing { dc_removal, band_pass, noise_gate, normalize };
+        let vad_config = VadConfig { backend, mode, frame, rate, soft, preprocessing, music_discrimination };
+
+        let source = if rdr.read_u8()? != 0 {
+            let sample_rate = rdr.read_u32::<LittleEndian>()?;
+            let channels = rdr.read_u32::<LittleEndian>()? as usize;
+            let has_name = rdr.read_u8()? != 0;
+            let name_len = rdr.read_u32::<LittleEndian>()? as usize;
+            let mut name = vec![0u8; name_len.min(bytes.len())];
+            rdr.read_exact(&mut name)?;
+            let name = String::from_utf8(name).map_err(|e| invalid(format!("invalid source name (msg='{}')", e)))?;
+            Some(ActivitySource { name: if has_name { Some(name) } else { None }, sample_rate, channels })
         } else {
-            data
+            None
         };
 
-        // Perform morphological 'opening' operation to remove noise
-        let data = if closing_radius > 0 {
-            let closing_clone = pad(&data, closing_radius);
-            unpad(&morph_closing(&closing_clone, closing_radius), closing_radius)
+        let data = read_bits(&mut rdr)?;
+        let known = read_bits(&mut rdr)?;
+        let likelihood = if rdr.read_u8()? != 0 {
+            read_f32s(&mut rdr)?
         } else {
-            data
+            data.iter().map(|d| if *d { 1.0 } else { 0.0 }).collect()
         };
+        let music = read_f32s(&mut rdr)?;
+        if known.len() != data.len() || likelihood.len() != data.len() || !(music.is_empty() || music.len() == data.len()) {
+            return Err(invalid(format!("inconsistent voice-activity lengths (data={}, known={}, likelihood={}, music={})",
+                data.len(), known.len(), likelihood.len(), music.len())));
+        }
+        Ok(VoiceActivity { data, likelihood, known, music, start_millis, chunk_millis, vad_config, source })
+    }
+}
+
+///
+/// Represents an error reading voice-activity from disk
+/// 
+#[derive(Debug)]
+pub enum VoiceActivityLoadError {
+    FileNotFound { path: String },
+    ReadError { cause: io::Error, path: String },
+    DeserializeError { cause: io::Error }
+}
+
+impl Error for VoiceActivityLoadError {}
+
+impl fmt::Display for VoiceActivityLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileNotFound { path } => write!(f, "Voice-activity file does not exist (path='{}')", path),
+            ReadError { cause, path } => write!(f, "Error reading voice-activity from disk (msg='{}', path='{}')", cause, path),
+            DeserializeError { cause } => write!(f, "Error parsing voice-activity from bytes (msg='{}')", cause)
+        }
+    }
+}
+
+///
+/// Represents an error writing voice-activity to disk
+/// 
+#[derive(Debug)]
+pub enum VoiceActivitySaveError {
+    SerializeError { cause: io::Error },
+    WriteError { cause: io::Error }
+}
+
+impl Error for VoiceActivitySaveError {}
+
+impl fmt::Display for VoiceActivitySaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerializeError { cause } => write!(f, "Error serializing voice-activity to bytes (msg='{}')", cause),
+            WriteError { cause } => write!(f, "Error saving voice-activity to disk (msg='{}')", cause)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity(data: Vec<bool>, likelihood: Vec<f32>) -> VoiceActivity {
+        VoiceActivity {
+            known: vec![true; data.len()],
+            music: vec![],
+            data,
+            likelihood,
+            start_millis: 0,
+            chunk_millis: 10,
+            vad_config: VadConfig::default(),
+            source: None
+        }
+    }
+
+    fn round_trip(activity: &VoiceActivity) -> VoiceActivity {
+        let bytes: Vec<u8> = activity.try_into().unwrap();
+        VoiceActivity::try_from(bytes.as_slice()).unwrap()
+    }
+
+    fn load_error(activity: &VoiceActivity) -> String {
+        let bytes: Vec<u8> = activity.try_into().unwrap();
+        let err = VoiceActivity::try_from(bytes.as_slice()).err().unwrap();
+        assert_eq!(err.kind(), InvalidData);
+        err.to_string()
+    }
+
+    #[test]
+    fn round_trips_every_setting() {
+        let original = VoiceActivity {
+            known: vec![true, true, false, true, true, true, true, true, false],
+            music: vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8],
+            start_millis: -250,
+            chunk_millis: 20,
+            vad_config: VadConfig {
+                backend: VadBackend::Energy,
+                mode: VadMode::VeryAggressive,
+                frame: VadFrame::Frame20ms,
+                rate: VadRate::Rate32kHz,
+                soft: true,
+                preprocessing: Preprocessing {
+                    dc_removal: true,
+                    band_pass: Some(BandPass { low_hz: 150.0, high_hz: 3500.0 }),
+                    noise_gate: Some(4.5),
+                    normalize: Some(-18.0)
+                },
+                music_discrimination: true
+            },
+            source: Some(ActivitySource { name: Some("film.mkv".to_string()), sample_rate: 48000, channels: 6 }),
+            ..activity(
+                vec![false, true, true, false, true, true, true, false, false],
+                vec![0.1, 0.9, 0.6, 0.4, 0.7, 1.0, 0.8, 0.2, 0.0])
+        };
+        let loaded = round_trip(&original);
+        assert_eq!(loaded.data, original.data);
+        assert_eq!(loaded.likelihood, original.likelihood);
+        assert_eq!(loaded.known, original.known);
+        assert_eq!(loaded.music, original.music);
+        assert_eq!(loaded.start_millis, original.start_millis);
+        assert_eq!(loaded.chunk_millis, original.chunk_millis);
+        assert_eq!(loaded.vad_config, original.vad_config);
+        assert_eq!(loaded.source, original.source);
+    }
+
+    #[test]
+    fn round_trips_likelihood_given_by_data() {
+        let original = VoiceActivity {
+            source: Some(ActivitySource { name: None, sample_rate: 8000, channels: 1 }),
+            ..activity(vec![true, false, true], vec![1.0, 0.0, 1.0])
+        };
+        let bytes: Vec<u8> = (&original).try_into().unwrap();
+        let with_likelihood = VoiceActivity { likelihood: vec![1.0, 0.0, 0.5], ..original.clone() };
+        let bytes_with_likelihood: Vec<u8> = (&with_likelihood).try_into().unwrap();
+        assert!(bytes.len() < bytes_with_likelihood.len());
+
+        let loaded = round_trip(&original);
+        assert_eq!(loaded.data, original.data);
+        assert_eq!(loaded.likelihood, original.likelihood);
+        assert_eq!(loaded.source, original.source);
+        assert_eq!(loaded.vad_config, VadConfig::default());
+        assert!(round_trip(&activity(vec![], vec![])).data.is_empty());
+    }
+
+    #[test]
+    fn rejects_zero_chunk_duration() {
+        let invalid = VoiceActivity { chunk_millis: 0, ..activity(vec![true], vec![1.0]) };
+        assert_eq!(load_error(&invalid), "chunk duration must be positive");
+    }
 
-        VoiceActivity { data, chunk_millis: self.chunk_millis }
+    #[test]
+    fn rejects_inconsistent_lengths() {
+        let base = activity(vec![true, false], vec![0.9, 0.1]);
+        let short_known = VoiceActivity { known: vec![true], ..base.clone() };
+        assert_eq!(load_error(&short_known), "inconsistent voice-activity lengths (data=2, known=1, likelihood=2, music=0)");
+        let long_likelihood = VoiceActivity { likelihood: vec![0.9, 0.1, 0.5], ..base.clone() };
+        assert_eq!(load_error(&long_likelihood), "inconsistent voice-activity lengths (data=2, known=2, likelihood=3, music=0)");
+        let short_music = VoiceActivity { music: vec![0.5], ..base };
+        assert_eq!(load_error(&short_music), "inconsistent voice-activity lengths (data=2, known=2, likelihood=2, music=1)");
     }
 }
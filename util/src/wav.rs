@@ -0,0 +1,482 @@
+#![allow(clippy::needless_arbitrary_self_type)]
+
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::io;
+use std::io::{Read, BufReader};
+use std::io::ErrorKind::{NotFound, Interrupted};
+use std::error::Error;
+
+use log::warn;
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::{AudioSink, AudioSinkError, AudioFormat, ChannelLayout, Downmix, SampleFormat, VadConfig, VoiceActivity, VoiceDetector};
+
+use WavError::*;
+
+/// Format tag of integer PCM audio
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+
+/// Format tag of IEEE float audio
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+
+/// Format tag of the extensible header, whose sub-format holds the actual format tag
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Largest fmt chunk accepted, leaving room for extensions beyond the 40 bytes of the
+/// extensible header, so that a corrupt size cannot cause an arbitrarily large allocation
+const MAX_FMT_BYTES: u32 = 64;
+
+/// Bytes read from the data chunk at a time (rounded down to whole frames)
+const READ_BUFFER_BYTES: usize = 64 * 1024;
+
+///
+/// Reads RIFF/WAVE audio and streams it into an `AudioSink`
+/// 
+/// Integer PCM of 8, 16, 24 and 32 bits and 32 and 64-bit float samples are supported at
+/// any sample rate and channel count accepted by `AudioSink`, in both the classic and the
+/// extensible header. Multi-channel audio is mixed down according to the channel mask of
+/// extensible headers (see `ChannelLayout`), or the conventional layout for the channel
+/// count otherwise.
+/// 
+/// The header is read up to the start of the sample data when the reader is created.
+/// Samples are then read on demand without seeking, so that any `Read` will do.
+/// 
+pub struct WavReader<R: Read> {
+    reader: R,
+    format: AudioFormat,
+    encoding: WavEncoding,
+    remaining_bytes: Option<u64>
+}
+
+impl WavReader<BufReader<File>> {
+
+    ///
+    /// Opens the WAVE file at the given path and reads its header
+    /// 
+    pub fn open(filename: &str) -> Result<Self, WavError> {
+        let file = File::open(Path::new(filename))
+            .map_err(|cause| match cause.kind() {
+                NotFound => FileNotFound { path: filename.to_string() },
+                _ => ReadError { cause }
+            })?;
+        WavReader::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> WavReader<R> {
+
+    ///
+    /// Reads the header of WAVE audio, leaving `reader` at the start of the sample data
+    /// 
+    pub fn new(mut reader: R) -> Result<Self, WavError> {
+        let mut riff = [0u8; 12];
+        reader.read_exact(&mut riff).map_err(|cause| ReadError { cause })?;
+        if &riff[0 .. 4] != b"RIFF" || &riff[8 .. 12] != b"WAVE" {
+            return Err(InvalidHeader { msg: String::from("not a RIFF/WAVE file") });
+        }
+
+        let mut fmt: Option<(AudioFormat, WavEncoding)> = None;
+        loop {
+            let mut chunk = [0u8; 8];
+            reader.read_exact(&mut chunk).map_err(|cause| match cause.kind() {
+                io::ErrorKind::UnexpectedEof => InvalidHeader { msg: String::from("no data chunk") },
+                _ => ReadError { cause }
+            })?;
+            let size = LittleEndian::read_u32(&chunk[4 ..]);
+
+            match &chunk[0 .. 4] {
+                b"fmt " => {
+                    if size > MAX_FMT_BYTES {
+                        return Err(InvalidHeader { msg: format!("fmt chunk too long (length={})", size) });
+                    }
+                    let mut body = vec![0u8; size as usize];
+                    reader.read_exact(&mut body).map_err(|cause| ReadError { cause })?;
+                    fmt = Some(parse_fmt(&body)?);
+                    skip(&mut reader, size as u64 % 2)?;
+                },
+                b"data" => {
+                    let (format, encoding) = fmt.ok_or_else(|| InvalidHeader { msg: String::from("data chunk precedes fmt chunk") })?;
+
+                    // Streamed files may leave the size of the data chunk unset
+                    let remaining_bytes = match size {
+                        0 | u32::MAX => None,
+                        size => Some(size as u64)
+                    };
+                    return Ok(WavReader { reader, format, encoding, remaining_bytes });
+                },
+                _ => skip(&mut reader, size as u64 + size as u64 % 2)?
+            }
+        }
+    }
+
+    ///
+    /// Format of the audio, as passed to `AudioSink::with_config`
+    /// 
+    pub fn format(self: &Self) -> &AudioFormat {
+        &self.format
+    }
+
+    ///
+    /// Duration of the audio given by the header (millis), or `None` if the header does not
+    /// specify the length of the sample data
+    /// 
+    pub fn duration_millis(self: &Self) -> Option<u64> {
+        let frame_bytes = (self.encoding.bytes() * self.format.channels) as u64;
+        self.remaining_bytes.map(|bytes| bytes / frame_bytes * 1000 / self.format.sample_rate as u64)
+    }
+
+    ///
+    /// Reads the remaining samples and sends them to the given sink, which must accept
+    /// `self.format()`. The sink is left open.
+    /// 
+    /// A trailing partial frame is dropped.
+    /// 
+    pub fn send_to<D: VoiceDetector>(self: &mut Self, sink: &mut AudioSink<D>) -> Result<(), WavError> {
+        if sink.channels() != self.format.channels {
+            let msg = format!("sink must accept the channel count of the WAVE audio (sink={}, wav={})", sink.channels(), self.format.channels);
+            return Err(SinkError { cause: AudioSinkError::InvalidFormat { msg } });
+        }
+        let frame_bytes = self.encoding.bytes() * self.format.channels;
+        let mut buffer = vec![0u8; (READ_BUFFER_BYTES / frame_bytes).max(1) * frame_bytes];
+        let mut samples = Vec::with_capacity(buffer.len() / self.encoding.bytes());
+        let mut pending = 0;
+        loop {
+            let limit = match self.remaining_bytes {
+                Some(remaining) => (pending as u64 + remaining).min(buffer.len() as u64) as usize,
+                None => buffer.len()
+            };
+            let read = match self.reader.read(&mut buffer[pending .. limit]) {
+                Ok(read) => read,
+                Err(e) if e.kind() == Interrupted => continue,
+                Err(cause) => return Err(ReadError { cause })
+            };
+            if let Some(remaining) = self.remaining_bytes.as_mut() {
+                *remaining -= read as u64;
+            }
+            pending += read;
+
+            let whole = pending / frame_bytes * frame_bytes;
+            let done = read == 0 || self.remaining_bytes == Some(0);
+            if whole == buffer.len() || (done && whole > 0) {
+                samples.clear();
+                samples.extend(buffer[.. whole].chunks_exact(self.encoding.bytes()).map(|b| self.encoding.decode(b)));
+                sink.send_samples(&samples).map_err(|cause| SinkError { cause })?;
+                buffer.copy_within(whole .. pending, 0);
+                pending -= whole;
+            }
+            if done {
+                if pending > 0 {
+                    warn!("Dropping partial frame at end of WAVE data ({} bytes)", pending);
+                }
+                if self.remaining_bytes.map(|r| r > 0).unwrap_or(false) {
+                    warn!("WAVE data ends before the length given in its header");
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    ///
+    /// Reads the remaining samples and computes their voice-activity with the given settings
+    /// 
+    pub fn voice_activity(mut self: Self, vad_config: VadConfig) -> Result<VoiceActivity, WavError> {
+        let mut sink = AudioSink::with_config(&self.format, vad_config)
+            .map_err(|cause| SinkError { cause })?;
+        self.send_to(&mut sink)?;
+        sink.close().map_err(|cause| SinkError { cause })?;
+        sink.try_voice_activity().map_err(|cause| SinkError { cause })
+    }
+}
+
+///
+/// Encoding of samples within the data chunk
+/// 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WavEncoding { U8, I16, I24, I32, F32, F64 }
+
+impl WavEncoding {
+    fn bytes(self: &Self) -> usize {
+        match self {
+            WavEncoding::U8 => 1,
+            WavEncoding::I16 => 2,
+            WavEncoding::I24 => 3,
+            WavEncoding::I32 | WavEncoding::F32 => 4,
+            WavEncoding::F64 => 8
+        }
+    }
+
+    fn decode(self: &Self, bytes: &[u8]) -> i16 {
+        match self {
+            WavEncoding::U8 => SampleFormat::U8.decode(bytes),
+            WavEncoding::I16 => SampleFormat::I16.decode(bytes),
+            WavEncoding::I24 => LittleEndian::read_i16(&bytes[1 ..]),
+            WavEncoding::I32 => SampleFormat::I32.decode(bytes),
+            WavEncoding::F32 => SampleFormat::F32.decode(bytes),
+            WavEncoding::F64 => {
+                let s = LittleEndian::read_f64(bytes);
+                if s.is_nan() { 0 } else { (s * 32767.0).round().clamp(i16::MIN as f64, i16::MAX as f64) as i16 }
+            }
+        }
+    }
+}
+
+///
+/// Parses the body of a fmt chunk
+/// 
+fn parse_fmt(body: &[u8]) -> Result<(AudioFormat, WavEncoding), WavError> {
+    if body.len() < 16 {
+        return Err(InvalidHeader { msg: format!("fmt chunk too short (length={})", body.len()) });
+    }
+    let mut format_tag = LittleEndian::read_u16(&body[0 ..]);
+    let channels = LittleEndian::read_u16(&body[2 ..]) as usize;
+    let sample_rate = LittleEndian::read_u32(&body[4 ..]);
+    let block_align = LittleEndian::read_u16(&body[12 ..]) as usize;
+    let bits = LittleEndian::read_u16(&body[14 ..]);
+
+    let mut layout = ChannelLayout::for_channels(channels);
+    if format_tag == WAVE_FORMAT_EXTENSIBLE {
+        if body.len() < 40 {
+            return Err(InvalidHeader { msg: format!("extensible fmt chunk too short (length={})", body.len()) });
+        }
+        layout = channel_mask_layout(LittleEndian::read_u32(&body[20 ..]), channels);
+        format_tag = LittleEndian::read_u16(&body[24 ..]);
+    }
+
+    if sample_rate == 0 {
+        return Err(InvalidHeader { msg: String::from("sample rate must be positive") });
+    }
+
+    if channels == 0 || block_align == 0 || block_align / channels * channels != block_align {
+        return Err(InvalidHeader { msg: format!("block size must be a multiple of the channel count (block_align={}, channels={})", block_align, channels) });
+    }
+
+    // Samples narrower than their container (e.g. 20 bits in 24) are aligned to the most significant bit
+    let encoding = match (format_tag, block_align / channels) {
+        (WAVE_FORMAT_PCM, 1) => WavEncoding::U8,
+        (WAVE_FORMAT_PCM, 2) => WavEncoding::I16,
+        (WAVE_FORMAT_PCM, 3) => WavEncoding::I24,
+        (WAVE_FORMAT_PCM, 4) => WavEncoding::I32,
+        (WAVE_FORMAT_IEEE_FLOAT, 4) => WavEncoding::F32,
+        (WAVE_FORMAT_IEEE_FLOAT, 8) => WavEncoding::F64,
+        _ => return Err(UnsupportedEncoding { format_tag, bits })
+    };
+
+    let format = AudioFormat {
+        sample_rate,
+        channels,
+        sample_format: SampleFormat::I16,
+        layout,
+        downmix: Downmix::Dialogue
+    };
+    Ok((format, encoding))
+}
+
+///
+/// Maps the speaker positions of an extensible header's channel mask to a layout, if the
+/// channels are in an order `ChannelLayout` understands
+/// 
+fn channel_mask_layout(mask: u32, channels: usize) -> ChannelLayout {
+    const FRONT_LEFT: u32 = 0x1;
+    const FRONT_RIGHT: u32 = 0x2;
+    const FRONT_CENTER: u32 = 0x4;
+    const LOW_FREQUENCY: u32 = 0x8;
+    const BACK_LEFT: u32 = 0x10;
+    const BACK_RIGHT: u32 = 0x20;
+    const SIDE_LEFT: u32 = 0x200;
+    const SIDE_RIGHT: u32 = 0x400;
+
+    const FRONT: u32 = FRONT_LEFT | FRONT_RIGHT | FRONT_CENTER | LOW_FREQUENCY;
+
+    let layout = match mask {
+        0 => return ChannelLayout::for_channels(channels),
+        FRONT_CENTER => ChannelLayout::Mono,
+        m if m == FRONT_LEFT | FRONT_RIGHT => ChannelLayout::Stereo,
+        m if m == FRONT | BACK_LEFT | BACK_RIGHT || m == FRONT | SIDE_LEFT | SIDE_RIGHT => ChannelLayout::Surround51,
+        m if m == FRONT | BACK_LEFT | BACK_RIGHT | SIDE_LEFT | SIDE_RIGHT => ChannelLayout::Surround71,
+        _ => ChannelLayout::Unknown
+    };
+    match layout.channel_count() {
+        Some(n) if n != channels => ChannelLayout::Unknown,
+        _ => layout
+    }
+}
+
+///
+/// Discards the given number of bytes from the reader
+/// 
+fn skip<R: Read>(reader: &mut R, bytes: u64) -> Result<(), WavError> {
+    let skipped = io::copy(&mut reader.by_ref().take(bytes), &mut io::sink())
+        .map_err(|cause| ReadError { cause })?;
+    if skipped < bytes {
+        return Err(InvalidHeader { msg: String::from("unexpected end of file") });
+    }
+    Ok(())
+}
+
+///
+/// Represents an error reading WAVE audio
+/// 
+#[derive(Debug)]
+pub enum WavError {
+    FileNotFound { path: String },
+    ReadError { cause: io::Error },
+    InvalidHeader { msg: String },
+    UnsupportedEncoding { format_tag: u16, bits: u16 },
+    SinkError { cause: AudioSinkError }
+}
+
+impl Error for WavError {}
+
+impl fmt::Display for WavError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileNotFound { path } => write!(f, "WAVE file does not exist (path='{}')", path),
+            ReadError { cause } => write!(f, "Error reading WAVE audio (msg='{}')", cause),
+            InvalidHeader { msg } => write!(f, "Invalid WAVE header (msg='{}')", msg),
+            UnsupportedEncoding { format_tag, bits } => write!(f, "Unsupported WAVE sample encoding (format_tag={:#06x}, bits={})", format_tag, bits),
+            SinkError { cause } => write!(f, "Error processing WAVE audio (msg='{}')", cause)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Body of a classic fmt chunk for 16-bit integer PCM
+    fn pcm16_fmt(channels: u16, sample_rate: u32) -> Vec<u8> {
+        let mut body = vec![0u8; 16];
+        LittleEndian::write_u16(&mut body[0 ..], WAVE_FORMAT_PCM);
+        LittleEndian::write_u16(&mut body[2 ..], channels);
+        LittleEndian::write_u32(&mut body[4 ..], sample_rate);
+        LittleEndian::write_u32(&mut body[8 ..], sample_rate * channels as u32 * 2);
+        LittleEndian::write_u16(&mut body[12 ..], channels * 2);
+        LittleEndian::write_u16(&mut body[14 ..], 16);
+        body
+    }
+
+    /// Chunk with the given id, declared size and body, padded to an even length
+    fn chunk(id: &[u8; 4], size: u32, body: &[u8]) -> Vec<u8> {
+        let mut bytes = id.to_vec();
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    }
+
+    /// RIFF/WAVE file holding the given chunks
+    fn wav(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        bytes.extend_from_slice(b"WAVE");
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    fn samples(count: usize) -> Vec<u8> {
+        (0 .. count).flat_map(|i| ((i as i16 % 200 - 100) * 100).to_le_bytes().to_vec()).collect()
+    }
+
+    fn header_error(bytes: &[u8]) -> String {
+        match WavReader::new(bytes) {
+            Err(InvalidHeader { msg }) => msg,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("header accepted")
+        }
+    }
+
+    #[test]
+    fn reads_pcm16() {
+        let fmt = pcm16_fmt(1, 8000);
+        let data = samples(1600);
+        let bytes = wav(&[chunk(b"fmt ", 16, &fmt), chunk(b"data", data.len() as u32, &data)]);
+
+        let reader = WavReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.format().sample_rate, 8000);
+        assert_eq!(reader.format().channels, 1);
+        assert_eq!(reader.encoding, WavEncoding::I16);
+        assert_eq!(reader.duration_millis(), Some(200));
+
+        let activity = reader.voice_activity(VadConfig::default()).unwrap();
+        assert_eq!(activity.chunk_millis, 30);
+        assert_eq!(activity.data.len(), 7);
+    }
+
+    #[test]
+    fn skips_odd_sized_and_unknown_chunks() {
+        let fmt = pcm16_fmt(2, 16000);
+        let data = samples(3200);
+        let bytes = wav(&[
+            chunk(b"LIST", 3, b"abc"),
+            chunk(b"fmt ", 16, &fmt),
+            chunk(b"junk", 5, b"12345"),
+            chunk(b"data", data.len() as u32, &data)
+        ]);
+
+        let mut reader = WavReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.format().sample_rate, 16000);
+        assert_eq!(reader.format().channels, 2);
+        assert_eq!(reader.duration_millis(), Some(100));
+
+        let mut sink = AudioSink::with_config(reader.format(), VadConfig::default()).unwrap();
+        reader.send_to(&mut sink).unwrap();
+        assert_eq!(sink.samples_received(), 3200);
+    }
+
+    #[test]
+    fn rejects_missing_data_chunk() {
+        let bytes = wav(&[chunk(b"fmt ", 16, &pcm16_fmt(1, 8000))]);
+        assert_eq!(header_error(&bytes), "no data chunk");
+    }
+
+    #[test]
+    fn rejects_data_before_fmt() {
+        let bytes = wav(&[chunk(b"data", 4, &samples(2)), chunk(b"fmt ", 16, &pcm16_fmt(1, 8000))]);
+        assert_eq!(header_error(&bytes), "data chunk precedes fmt chunk");
+    }
+
+    #[test]
+    fn rejects_oversized_fmt_chunk() {
+        // The declared size is rejected before anything is allocated for it
+        let bytes = wav(&[chunk(b"fmt ", u32::MAX, &pcm16_fmt(1, 8000))]);
+        assert!(header_error(&bytes).starts_with("fmt chunk too long"));
+
+        let mut fmt = pcm16_fmt(1, 8000);
+        fmt.resize(MAX_FMT_BYTES as usize + 2, 0);
+        let bytes = wav(&[chunk(b"fmt ", fmt.len() as u32, &fmt), chunk(b"data", 4, &samples(2))]);
+        assert!(header_error(&bytes).starts_with("fmt chunk too long"));
+    }
+
+    #[test]
+    fn rejects_zero_sample_rate() {
+        let bytes = wav(&[chunk(b"fmt ", 16, &pcm16_fmt(1, 0)), chunk(b"data", 4, &samples(2))]);
+        assert_eq!(header_error(&bytes), "sample rate must be positive");
+    }
+
+    #[test]
+    fn rejects_truncated_chunk() {
+        let mut bytes = wav(&[chunk(b"fmt ", 16, &pcm16_fmt(1, 8000)), chunk(b"LIST", 100, &[0u8; 10])]);
+        bytes.truncate(bytes.len() - 1);
+        assert_eq!(header_error(&bytes), "unexpected end of file");
+    }
+
+    #[test]
+    fn reads_truncated_data_chunk() {
+        let data = samples(1000);
+        let mut bytes = wav(&[chunk(b"fmt ", 16, &pcm16_fmt(1, 8000)), chunk(b"data", 3200, &data)]);
+        bytes.push(0xff);
+
+        let mut reader = WavReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.duration_millis(), Some(200));
+
+        // Samples present are processed and the trailing partial sample dropped
+        let mut sink = AudioSink::with_config(reader.format(), VadConfig::default()).unwrap();
+        reader.send_to(&mut sink).unwrap();
+        assert_eq!(sink.samples_received(), 1000);
+    }
+}